@@ -0,0 +1,55 @@
+use crate::cache::read_config;
+
+/// Common nicknames, abbreviations and historical names, as `(alias, parameter)`.
+const BUILTIN_ALIASES: [(&str, &str); 34] = [
+    ("30th", "30th Street Station"),
+    ("30th St", "30th Street Station"),
+    ("Gray 30th Street", "30th Street Station"),
+    ("William H Gray III 30th Street", "30th Street Station"),
+    ("Suburban", "Suburban Station"),
+    ("Sub", "Suburban Station"),
+    ("Jeff", "Jefferson Station"),
+    ("Jefferson", "Jefferson Station"),
+    ("Market East", "Jefferson Station"),
+    ("Market East Station", "Jefferson Station"),
+    ("Temple", "Temple U"),
+    ("Penn Medicine", "Penn Medicine Station"),
+    ("University City", "Penn Medicine Station"),
+    ("North Philly", "North Philadelphia"),
+    ("Airport A", "Airport Terminal A"),
+    ("Airport B", "Airport Terminal B"),
+    ("Airport C", "Airport Terminal C-D"),
+    ("Airport D", "Airport Terminal C-D"),
+    ("Airport E", "Airport Terminal E-F"),
+    ("Airport F", "Airport Terminal E-F"),
+    ("Chester", "Chester TC"),
+    ("Fern Rock", "Fern Rock TC"),
+    ("FRTC", "Fern Rock TC"),
+    ("Norristown", "Norristown TC"),
+    ("NTC", "Norristown TC"),
+    ("Jenkintown", "Jenkintown-Wyncote"),
+    ("Wyncote", "Jenkintown-Wyncote"),
+    ("Trenton Transit Center", "Trenton"),
+    ("Wayne Junction", "Wayne Jct"),
+    ("Eastwick", "Eastwick Station"),
+    ("Fernwood-Yeadon", "Fernwood"),
+    ("Yeadon", "Fernwood"),
+    ("Newark Station", "Newark"),
+    ("Newark DE", "Newark"),
+];
+
+/// Returns the built-in aliases followed by the user's own.
+///
+/// User aliases live in `aliases` in the config directory, one `alias = station` per line,
+/// where the station is either its parameter or its full name. Lines starting with `#` are ignored.
+pub fn aliases() -> Vec<(String, String)> {
+    let mut aliases: Vec<(String, String)> = BUILTIN_ALIASES
+        .into_iter()
+        .map(|(alias, station)| (alias.to_owned(), station.to_owned()))
+        .collect();
+
+    aliases.extend(read_config("aliases", |alias, station| {
+        Some((alias.to_owned(), station.to_owned()))
+    }));
+    aliases
+}
@@ -0,0 +1,295 @@
+use crate::envelope;
+use crate::traits::{Parse, PrettyPrint};
+use crate::utils::{format_age, format_size, now};
+use anyhow::{Context, Result, anyhow};
+use colored::Colorize;
+use directories::ProjectDirs;
+use std::env;
+use std::fs;
+use std::fs::create_dir_all;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Default time to live for the station cache, a week.
+const DEFAULT_STATIONS_TTL_HOURS: u64 = 168;
+
+/// Default time to live for Septum's lines and per-line stations, a week.
+const DEFAULT_SEPTUM_TTL_HOURS: u64 = 168;
+
+/// Returns the cache directory used by `tst`, creating it if needed.
+///
+/// Defaults to the platform cache directory, and can be overridden with `TST_CACHE_DIR`.
+pub fn cache_dir() -> Result<PathBuf> {
+    let dir = match env::var("TST_CACHE_DIR") {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => app_dirs()?.cache_dir().to_path_buf(),
+    };
+    if !dir.exists() {
+        create_dir_all(&dir)?;
+    }
+    Ok(dir)
+}
+
+/// Returns the directory holding the user's own files (e.g, aliases), which `tst` only reads.
+pub fn config_dir() -> Result<PathBuf> {
+    Ok(app_dirs()?.config_dir().to_path_buf())
+}
+
+/// Reads the user's `name` file from the config directory, one `key = value` per line.
+///
+/// Blank lines and lines starting with `#` are skipped, and a missing file has no entries. A broken
+/// file is reported and ignored, since it should never stop `tst` from working.
+pub fn read_config<T>(name: &str, parse: impl Fn(&str, &str) -> Option<T>) -> Vec<T> {
+    let read = || -> Result<Vec<T>> {
+        let path = config_dir()?.join(name);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        fs::read_to_string(&path)?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                line.split_once('=')
+                    .and_then(|(key, value)| parse(key.trim(), value.trim()))
+                    .ok_or(anyhow!("Invalid line in {}: {}", path.display(), line))
+            })
+            .collect()
+    };
+
+    read().unwrap_or_else(|e| {
+        eprintln!("{} {}", format!("Ignoring user {}:", name).yellow(), e);
+        Vec::new()
+    })
+}
+
+/// Returns the directory for data `tst` keeps about its use (e.g, history), creating it if needed.
+pub fn data_dir() -> Result<PathBuf> {
+    let dir = app_dirs()?.data_dir().to_path_buf();
+    if !dir.exists() {
+        create_dir_all(&dir)?;
+    }
+    Ok(dir)
+}
+
+fn app_dirs() -> Result<ProjectDirs> {
+    ProjectDirs::from("com", "dotzenith", "TheSeptaTimes").ok_or(anyhow!("Unable to get AppDirs"))
+}
+
+/// Returns the directory holding the last known response for each query.
+pub fn responses_dir() -> Result<PathBuf> {
+    let dir = cache_dir()?.join("responses");
+    if !dir.exists() {
+        create_dir_all(&dir)?;
+    }
+    Ok(dir)
+}
+
+/// Returns the directory holding Septum's lines and per-line station lists.
+pub fn septum_dir() -> Result<PathBuf> {
+    let dir = cache_dir()?.join("septum");
+    if !dir.exists() {
+        create_dir_all(&dir)?;
+    }
+    Ok(dir)
+}
+
+/// Returns the path of the station cache.
+pub fn stations_path() -> Result<PathBuf> {
+    Ok(cache_dir()?.join("stations"))
+}
+
+/// Returns the path of the registry linking Septum stops to SEPTA stations.
+pub fn registry_path() -> Result<PathBuf> {
+    Ok(cache_dir()?.join("station_registry"))
+}
+
+/// How long the station cache stays fresh, in seconds.
+///
+/// Defaults to a week, and can be overridden with `TST_STATIONS_TTL` as a number of hours.
+pub fn stations_ttl() -> Result<u64> {
+    ttl_from_env("TST_STATIONS_TTL", DEFAULT_STATIONS_TTL_HOURS)
+}
+
+/// How long Septum's lines and per-line stations stay fresh, in seconds.
+///
+/// Defaults to a week, and can be overridden with `TST_SEPTUM_TTL` as a number of hours.
+pub fn septum_ttl() -> Result<u64> {
+    ttl_from_env("TST_SEPTUM_TTL", DEFAULT_SEPTUM_TTL_HOURS)
+}
+
+fn ttl_from_env(var: &str, default_hours: u64) -> Result<u64> {
+    let hours = match env::var(var) {
+        Ok(hours) => hours
+            .trim()
+            .parse::<u64>()
+            .with_context(|| format!("{} must be a whole number of hours", var))?,
+        Err(_) => default_hours,
+    };
+    Ok(hours * 3600)
+}
+
+/// Removes the station cache, along with the registry linking Septum stops to it.
+pub fn clear_stations() -> Result<()> {
+    for path in [stations_path()?, registry_path()?] {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Removes Septum's cached lines and per-line stations.
+pub fn clear_septum() -> Result<()> {
+    let dir = septum_dir()?;
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+/// Removes every cached response.
+pub fn clear_responses() -> Result<()> {
+    let dir = responses_dir()?;
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+/// Seconds since the given file was last modified.
+pub fn file_age(path: &Path) -> Result<u64> {
+    let modified = fs::metadata(path)?.modified().context("Unsupported platform")?;
+    Ok(SystemTime::now()
+        .duration_since(modified)
+        .context("Time went backwards")?
+        .as_secs())
+}
+
+/// A summary of one of the caches on disk.
+pub struct CacheEntry {
+    name: &'static str,
+    path: PathBuf,
+    files: usize,
+    size: u64,
+    newest: Option<u64>,
+    oldest: Option<u64>,
+    source: String,
+}
+
+/// Everything `tst` keeps in its cache directory.
+pub struct CacheInfo(pub Vec<CacheEntry>);
+
+impl CacheInfo {
+    pub fn get() -> Result<CacheInfo> {
+        // The registry is cleared along with the stations, so it's counted with them
+        let stations_path = stations_path()?;
+        let stations_files: Vec<PathBuf> = [stations_path.clone(), registry_path()?]
+            .into_iter()
+            .filter(|path| path.exists())
+            .collect();
+        let stations_source = envelope::read_header(&stations_path)
+            .map(|header| header.source)
+            .ok()
+            .filter(|source| !source.is_empty())
+            .unwrap_or("Septum /stations".to_owned());
+        let stations = Self::summarize(
+            "Stations",
+            stations_path,
+            &stations_files,
+            format!(
+                "{} and the stops linked to them, fresh for {}",
+                stations_source,
+                format_age(stations_ttl()?)
+            ),
+        )?;
+
+        let septum_path = septum_dir()?;
+        let septum = Self::summarize(
+            "Septum",
+            septum_path.clone(),
+            &Self::files_in(&septum_path)?,
+            format!(
+                "Septum lines and per-line stations, fresh for {}",
+                format_age(septum_ttl()?)
+            ),
+        )?;
+
+        let responses_path = responses_dir()?;
+        let responses = Self::summarize(
+            "Responses",
+            responses_path.clone(),
+            &Self::files_in(&responses_path)?,
+            "Last known SEPTA and Septum responses".to_owned(),
+        )?;
+
+        Ok(CacheInfo(vec![stations, septum, responses]))
+    }
+
+    fn files_in(dir: &Path) -> Result<Vec<PathBuf>> {
+        Ok(fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect())
+    }
+
+    fn summarize(name: &'static str, path: PathBuf, files: &[PathBuf], source: String) -> Result<CacheEntry> {
+        let mut size = 0;
+        let mut ages = Vec::new();
+        for file in files {
+            size += fs::metadata(file)?.len();
+            // Files that predate the envelope only have their modification time to go by
+            let age = match envelope::read_header(file) {
+                Ok(header) => now()?.saturating_sub(header.fetched_at),
+                Err(_) => file_age(file)?,
+            };
+            ages.push(age);
+        }
+
+        Ok(CacheEntry {
+            name,
+            path,
+            files: files.len(),
+            size,
+            newest: ages.iter().min().copied(),
+            oldest: ages.iter().max().copied(),
+            source,
+        })
+    }
+}
+
+impl Parse for CacheInfo {
+    fn parse(&self) -> Vec<String> {
+        self.0
+            .iter()
+            .map(|entry| {
+                let age = match (entry.newest, entry.oldest) {
+                    (Some(newest), Some(oldest)) if newest == oldest => format!("{} old", format_age(newest)),
+                    (Some(newest), Some(oldest)) => {
+                        format!("{} to {} old", format_age(newest), format_age(oldest))
+                    }
+                    (_, _) => "Empty".to_owned(),
+                };
+                format!(
+                    "{}\n  {:<8}{}\n  {:<8}{} files, {}\n  {:<8}{}\n  {:<8}{}",
+                    entry.name.yellow().bold(),
+                    "Path",
+                    entry.path.display(),
+                    "Size",
+                    entry.files,
+                    format_size(entry.size),
+                    "Age",
+                    age,
+                    "Source",
+                    entry.source,
+                )
+            })
+            .collect()
+    }
+}
+
+impl PrettyPrint for CacheInfo {
+    fn print(&self) {
+        for entry in self.parse().iter() {
+            println!("{entry}");
+        }
+    }
+}
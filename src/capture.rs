@@ -0,0 +1,173 @@
+use crate::utils::hash_key;
+use anyhow::{Context, Result, anyhow};
+use std::fs;
+use std::fs::create_dir_all;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use url::Url;
+
+const INDEX_FILE: &str = "index";
+
+/// A directory of raw API responses, recorded with `--record` and served back with `--replay`.
+///
+/// Each response body is stored verbatim in its own `<hash>.json` file so captures can be
+/// inspected or edited by hand, and `index` maps every file back to the request it answers.
+pub struct Capture {
+    dir: PathBuf,
+    /// Held while rewriting `index`, since requests can be recorded from several threads
+    index: Mutex<()>,
+}
+
+impl Capture {
+    /// Opens an existing capture directory.
+    pub fn open(dir: &Path) -> Result<Self> {
+        if !dir.is_dir() {
+            return Err(anyhow!("{} is not a capture directory", dir.display()));
+        }
+        Ok(Capture::new(dir))
+    }
+
+    /// Opens a capture directory for recording, creating it if needed.
+    pub fn create(dir: &Path) -> Result<Self> {
+        if !dir.exists() {
+            create_dir_all(dir)?;
+        }
+        Ok(Capture::new(dir))
+    }
+
+    fn new(dir: &Path) -> Self {
+        Capture {
+            dir: dir.to_path_buf(),
+            index: Mutex::new(()),
+        }
+    }
+
+    /// Returns the recorded body for the given request URL.
+    pub fn get(&self, url: &str) -> Result<String> {
+        let key = capture_key(url)?;
+        let file = self
+            .entries()?
+            .into_iter()
+            .find(|(entry, _)| *entry == key)
+            .map(|(_, file)| file)
+            .ok_or(anyhow!("No recorded response for {}", key))?;
+        Ok(fs::read_to_string(file)?)
+    }
+
+    /// Returns the recorded body for a request key, falling back to a recording of the same
+    /// path when this exact query was never recorded.
+    pub fn lookup(&self, key: &str) -> Result<String> {
+        let entries = self.entries()?;
+        let file = find_entry(&entries, key).ok_or(anyhow!("No recorded response for {}", key))?;
+        Ok(fs::read_to_string(file)?)
+    }
+
+    /// Stores the body for the given request URL, replacing any previous recording of it.
+    pub fn record(&self, url: &str, body: &str) -> Result<()> {
+        let key = capture_key(url)?;
+        let file_name = format!("{:016x}.json", hash_key(&key));
+        fs::write(self.dir.join(&file_name), body)?;
+
+        let _index = self.index.lock().map_err(|_| anyhow!("Capture index lock poisoned"))?;
+        let mut entries: Vec<(String, String)> = self
+            .read_index()?
+            .into_iter()
+            .filter(|(entry, _)| *entry != key)
+            .collect();
+        entries.push((key, file_name));
+
+        let index: String = entries
+            .iter()
+            .map(|(key, file)| format!("{}\t{}\n", file, key))
+            .collect();
+        fs::write(self.dir.join(INDEX_FILE), index)?;
+        Ok(())
+    }
+
+    /// All recorded requests along with the file holding each response.
+    pub fn entries(&self) -> Result<Vec<(String, PathBuf)>> {
+        Ok(self
+            .read_index()?
+            .into_iter()
+            .map(|(key, file)| (key, self.dir.join(file)))
+            .collect())
+    }
+
+    fn read_index(&self) -> Result<Vec<(String, String)>> {
+        let path = self.dir.join(INDEX_FILE);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let index = fs::read_to_string(&path).with_context(|| format!("Unable to read {}", path.display()))?;
+        Ok(index
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .map(|(file, key)| (key.to_owned(), file.to_owned()))
+            .collect())
+    }
+}
+
+/// Identifies a request by its path and query only, so a capture recorded against one
+/// host can be replayed regardless of which base URL is configured.
+pub fn capture_key(url: &str) -> Result<String> {
+    let url = Url::parse(url)?;
+    Ok(match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_owned(),
+    })
+}
+
+/// Finds the entry recorded for `key`, or failing that one for the same path, preferring one without a query.
+pub fn find_entry<'a, T>(entries: &'a [(String, T)], key: &str) -> Option<&'a T> {
+    let path = key.split_once('?').map_or(key, |(path, _)| path);
+    entries
+        .iter()
+        .find(|(entry, _)| entry == key)
+        // An entry without a query is meant as the catch-all for its path
+        .or_else(|| entries.iter().find(|(entry, _)| entry == path))
+        .or_else(|| {
+            entries
+                .iter()
+                .find(|(entry, _)| entry.split_once('?').map_or(entry.as_str(), |(path, _)| path) == path)
+        })
+        .map(|(_, value)| value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(keys: &[&str]) -> Vec<(String, usize)> {
+        keys.iter()
+            .enumerate()
+            .map(|(index, key)| (key.to_string(), index))
+            .collect()
+    }
+
+    #[test]
+    fn prefers_the_exact_entry() {
+        let entries = entries(&["/api/stations?line=TRE", "/api/stations", "/api/stations?line=PAO"]);
+        assert_eq!(find_entry(&entries, "/api/stations?line=PAO"), Some(&2));
+    }
+
+    #[test]
+    fn falls_back_to_the_entry_without_a_query() {
+        let entries = entries(&["/api/stations?line=TRE", "/api/stations", "/api/lines"]);
+        assert_eq!(find_entry(&entries, "/api/stations?line=CYN"), Some(&1));
+    }
+
+    #[test]
+    fn falls_back_to_any_entry_for_the_path() {
+        let entries = entries(&["/api/lines", "/api/stations?line=TRE"]);
+        assert_eq!(find_entry(&entries, "/api/stations?line=CYN"), Some(&1));
+        assert_eq!(find_entry(&entries, "/api/schedule"), None);
+    }
+
+    #[test]
+    fn keys_ignore_the_host() {
+        assert_eq!(
+            capture_key("http://127.0.0.1:8080/api/stations?line=TRE").unwrap(),
+            capture_key("https://example.com/api/stations?line=TRE").unwrap()
+        );
+    }
+}
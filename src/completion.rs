@@ -0,0 +1,111 @@
+use crate::Cli;
+use crate::fetch::{self, FetchMode};
+use crate::septum::{ScheduleDirection, SeptumMisc};
+use crate::stations::StationsManager;
+use clap::{Command, CommandFactory};
+use clap_complete::engine::CompletionCandidate;
+use std::env;
+use std::iter;
+
+/// Builds the command to complete against, making sure completing never touches the network.
+///
+/// Only called when the shell asks for completions, so regular runs are unaffected.
+pub fn command() -> Command {
+    let _ = fetch::set_mode(FetchMode::Cached);
+    Cli::command()
+}
+
+/// Station names and their aliases, from the station cache.
+pub fn stations() -> Vec<CompletionCandidate> {
+    StationsManager::new()
+        .get_stations()
+        .iter()
+        .flat_map(|station| {
+            let name = (station.name != station.parameter).then(|| station.name.clone().into());
+            let aliases = station.aliases.iter().map(|alias| {
+                CompletionCandidate::new(alias).help(Some(format!("Alias for {}", station.parameter).into()))
+            });
+            iter::once(CompletionCandidate::new(&station.parameter).help(name)).chain(aliases)
+        })
+        .collect()
+}
+
+/// Line codes, from the last known Septum response.
+pub fn lines() -> Vec<CompletionCandidate> {
+    let Ok(lines) = SeptumMisc::new().and_then(|septum| septum.get_lines()) else {
+        return Vec::new();
+    };
+    lines
+        .0
+        .into_iter()
+        .map(|line| CompletionCandidate::new(line.line_code).help(Some(line.line_name.into())))
+        .collect()
+}
+
+/// Arguments to `tst extra schedule`, a line or a station first, then stations on that line.
+///
+/// Stations come from the last known Septum response when a line was given, from the station
+/// cache otherwise.
+pub fn schedule_args() -> Vec<CompletionCandidate> {
+    // Completers only get the current word, the rest of the command line has to be dug out of
+    // the arguments the shell passed along
+    let words: Vec<String> = env::args().skip_while(|arg| arg != "--").collect();
+    let valued = valued_options();
+    let mut given: Vec<&String> = Vec::new();
+    let mut skip_value = false;
+    for word in words.iter().skip_while(|word| *word != "schedule").skip(1) {
+        match word.starts_with('-') {
+            true => skip_value = valued.contains(word),
+            false if skip_value => skip_value = false,
+            false => given.push(word),
+        }
+    }
+    // The last word is the one being completed
+    let Some((first, _)) = given.split_last().and_then(|(_, previous)| previous.split_first()) else {
+        let mut candidates = lines();
+        candidates.extend(stations());
+        return candidates;
+    };
+
+    let direction = match words.iter().any(|word| word == "--outbound") {
+        true => ScheduleDirection::Outbound,
+        false => ScheduleDirection::Inbound,
+    };
+    let Ok(septum) = SeptumMisc::new() else {
+        return stations();
+    };
+    let line = septum.get_lines().ok().and_then(|lines| {
+        lines
+            .0
+            .into_iter()
+            .find(|line| line.line_code.eq_ignore_ascii_case(first))
+    });
+    let Some(line) = line else {
+        return stations();
+    };
+
+    let Ok(stops) = septum.get_stations_for_line(&line.line_code, &direction) else {
+        return Vec::new();
+    };
+    stops
+        .into_iter()
+        .map(|stop| CompletionCandidate::new(stop.stop_name))
+        .collect()
+}
+
+/// Flags of `tst extra schedule` that take a value as the next word (e.g, `--date 2026-11-26`).
+fn valued_options() -> Vec<String> {
+    let cli = Cli::command();
+    let schedule = cli
+        .find_subcommand("extra")
+        .and_then(|extra| extra.find_subcommand("schedule"));
+    cli.get_arguments()
+        .chain(schedule.into_iter().flat_map(Command::get_arguments))
+        .filter(|arg| !arg.is_positional() && arg.get_action().takes_values())
+        .flat_map(|arg| {
+            let long = arg.get_long().map(|long| format!("--{long}"));
+            let short = arg.get_short().map(|short| format!("-{short}"));
+            long.into_iter().chain(short)
+        })
+        .collect()
+}
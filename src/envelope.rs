@@ -0,0 +1,211 @@
+use crate::cache::file_age;
+use crate::utils::{hash_bytes, now};
+use anyhow::{Context, Result, anyhow};
+use bincode::Options;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Every cache file starts with these bytes, anything else predates the envelope.
+const MAGIC: [u8; 4] = *b"TSTC";
+
+/// Number of files written so far by this process, to keep temporary files apart.
+static WRITES: AtomicU64 = AtomicU64::new(0);
+
+/// Describes the payload that follows it in a cache file.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Header {
+    /// Version of the payload's shape, bumped whenever it changes
+    pub version: u32,
+    /// Unix timestamp of when the payload was fetched
+    pub fetched_at: u64,
+    /// Where the payload came from, usually a URL
+    pub source: String,
+    checksum: u64,
+}
+
+/// A cache file, read back from disk.
+pub struct Envelope<T> {
+    pub header: Header,
+    pub payload: T,
+}
+
+impl<T> Envelope<T> {
+    /// Seconds since the payload was fetched.
+    pub fn age(&self) -> u64 {
+        now().unwrap_or(0).saturating_sub(self.header.fetched_at)
+    }
+}
+
+/// Writes `payload` to `path` along with a header describing it.
+///
+/// The file is written next to `path` under a name unique to this write, and renamed over it,
+/// so a reader never sees half a file and concurrent writers never share one.
+pub fn write<T: Serialize>(path: &Path, version: u32, source: &str, payload: &T) -> Result<()> {
+    write_with(path, version, now()?, source, payload)?;
+    Ok(())
+}
+
+fn write_with<T: Serialize>(path: &Path, version: u32, fetched_at: u64, source: &str, payload: &T) -> Result<Header> {
+    let payload = bincode::serialize(payload)?;
+    let header = Header {
+        version,
+        fetched_at,
+        source: source.to_owned(),
+        checksum: hash_bytes(&payload),
+    };
+
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend(bincode::serialize(&header)?);
+    bytes.extend(payload);
+
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(format!(
+        ".{}-{}.tmp",
+        process::id(),
+        WRITES.fetch_add(1, Ordering::Relaxed)
+    ));
+    let written = fs::write(&temporary, bytes).and_then(|_| fs::rename(&temporary, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    written.context("Unable to replace cache file")?;
+    Ok(header)
+}
+
+/// Reads just the header of a cache file, without checking its payload.
+pub fn read_header(path: &Path) -> Result<Header> {
+    let bytes = fs::read(path)?;
+    let (header, _) = split(&bytes)?.ok_or(anyhow!("Cache file predates the envelope format"))?;
+    Ok(header)
+}
+
+/// Reads a cache file written by `write`, upgrading it on disk if it's outdated.
+///
+/// Payloads written with an older `version`, or files that predate the envelope altogether
+/// (version `None`), are handed to `upgrade` to convert to the current shape.
+pub fn read<T, F>(path: &Path, version: u32, upgrade: F) -> Result<Envelope<T>>
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce(Option<u32>, &[u8]) -> Result<T>,
+{
+    let bytes = fs::read(path)?;
+
+    let Some((header, payload)) = split(&bytes)? else {
+        let payload = upgrade(None, &bytes).context("Unable to upgrade cache file")?;
+        // The modification time is the best guess there is for when legacy files were fetched
+        let fetched_at = now()?.saturating_sub(file_age(path)?);
+        let header = write_with(path, version, fetched_at, "", &payload)?;
+        return Ok(Envelope { header, payload });
+    };
+
+    if hash_bytes(payload) != header.checksum {
+        return Err(anyhow!("Cache file {} is corrupt", path.display()));
+    }
+    if header.version > version {
+        return Err(anyhow!(
+            "Cache file {} was written by a newer version of tst",
+            path.display()
+        ));
+    }
+    if header.version < version {
+        let payload = upgrade(Some(header.version), payload).context("Unable to upgrade cache file")?;
+        let header = write_with(path, version, header.fetched_at, &header.source, &payload)?;
+        return Ok(Envelope { header, payload });
+    }
+
+    Ok(Envelope {
+        payload: bincode::deserialize(payload)?,
+        header,
+    })
+}
+
+/// Decodes a payload that predates the envelope.
+///
+/// Unlike plain `bincode::deserialize`, every byte has to be used, so one legacy shape is
+/// never mistaken for another.
+pub fn decode_legacy<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    Ok(bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .deserialize(bytes)?)
+}
+
+/// Splits a cache file into its header and payload, or `None` if it has no header.
+fn split(bytes: &[u8]) -> Result<Option<(Header, &[u8])>> {
+    let Some(rest) = bytes.strip_prefix(&MAGIC) else {
+        return Ok(None);
+    };
+
+    let options = bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes();
+    let header: Header = options.deserialize(rest).context("Cache file header is corrupt")?;
+    let header_size = options.serialized_size(&header)? as usize;
+    Ok(Some((header, &rest[header_size..])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::path::PathBuf;
+
+    fn temporary_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("tst-envelope-{}-{}", process::id(), name))
+    }
+
+    fn no_upgrade(_: Option<u32>, _: &[u8]) -> Result<Vec<String>> {
+        Err(anyhow!("Nothing to upgrade"))
+    }
+
+    #[test]
+    fn reads_back_what_was_written() {
+        let path = temporary_path("roundtrip");
+        let payload = vec!["Trenton".to_owned()];
+        write(&path, 1, "source", &payload).unwrap();
+
+        let envelope = read(&path, 1, no_upgrade).unwrap();
+        assert_eq!(envelope.payload, payload);
+        assert_eq!(envelope.header.source, "source");
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn upgrades_legacy_files_in_place() {
+        let path = temporary_path("legacy");
+        let payload = vec!["30th Street Station (30th Street)".to_owned()];
+        fs::write(&path, bincode::serialize(&payload).unwrap()).unwrap();
+
+        let upgrade = |version: Option<u32>, bytes: &[u8]| {
+            assert_eq!(version, None);
+            decode_legacy::<Vec<String>>(bytes)
+        };
+        assert_eq!(read(&path, 2, upgrade).unwrap().payload, payload);
+        assert_eq!(read_header(&path).unwrap().version, 2);
+        assert_eq!(read(&path, 2, no_upgrade).unwrap().payload, payload);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn rejects_corrupt_and_newer_files() {
+        let path = temporary_path("corrupt");
+        write(&path, 1, "source", &vec!["Trenton".to_owned()]).unwrap();
+        assert!(read(&path, 0, no_upgrade).is_err());
+
+        let mut bytes = fs::read(&path).unwrap();
+        *bytes.last_mut().unwrap() ^= 0xff;
+        fs::write(&path, bytes).unwrap();
+        assert!(read(&path, 1, no_upgrade).is_err());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn tells_legacy_shapes_apart() {
+        let labels = bincode::serialize(&vec!["Trenton".to_owned()]).unwrap();
+        assert!(decode_legacy::<Vec<String>>(&labels).is_ok());
+        assert!(decode_legacy::<Vec<(String, String)>>(&labels).is_err());
+    }
+}
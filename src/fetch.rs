@@ -0,0 +1,135 @@
+use crate::cache::responses_dir;
+use crate::capture::Capture;
+use crate::envelope;
+use crate::utils::{format_age, hash_key};
+use anyhow::{Context, Result, anyhow};
+use colored::Colorize;
+use serde::de::DeserializeOwned;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+use ureq::Agent;
+use url::Url;
+
+static MODE: OnceLock<FetchMode> = OnceLock::new();
+static AGENT: OnceLock<Agent> = OnceLock::new();
+
+/// How long a request may take altogether, so a stalled connection falls back to the cache
+/// instead of hanging.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How requests to SEPTA and Septum are served.
+pub enum FetchMode {
+    /// Hit the network, falling back to the last known response on failure
+    Online,
+    /// Never touch the network, only serve responses from the cache
+    Offline,
+    /// Hit the network and store every raw response in the given capture
+    Record(Capture),
+    /// Never touch the network, only serve responses from the given capture
+    Replay(Capture),
+    /// Never touch the network, and quietly serve responses from the cache (e.g, while completing)
+    Cached,
+}
+
+/// Version of the response cache payload, the raw response body.
+const RESPONSE_FORMAT_VERSION: u32 = 1;
+
+/// Sets how requests are served for the rest of the run, can only be called once.
+pub fn set_mode(mode: FetchMode) -> Result<()> {
+    MODE.set(mode).map_err(|_| anyhow!("Fetch mode already set"))
+}
+
+fn mode() -> &'static FetchMode {
+    MODE.get_or_init(|| FetchMode::Online)
+}
+
+fn agent() -> &'static Agent {
+    AGENT.get_or_init(|| {
+        Agent::config_builder()
+            .timeout_global(Some(REQUEST_TIMEOUT))
+            .build()
+            .into()
+    })
+}
+
+/// Whether requests go through a capture, in which case other caches have to be bypassed so
+/// that every request is actually recorded or replayed.
+pub fn is_capturing() -> bool {
+    matches!(mode(), FetchMode::Record(_) | FetchMode::Replay(_))
+}
+
+/// Fetches and deserializes a JSON response.
+///
+/// Successful responses are cached per URL. If the request fails or times out, the response
+/// can't be parsed (e.g, an HTML error page), or if offline mode is on,
+/// the most recent cached response for the same URL is used instead and labeled with its age.
+pub fn get_json<T: DeserializeOwned>(url: &str) -> Result<T> {
+    match mode() {
+        FetchMode::Offline => {
+            return read_from_cache(url, Some("offline"))
+                .context("No cached response available for this query while offline");
+        }
+        FetchMode::Cached => return read_from_cache(url, None),
+        FetchMode::Replay(_) => return get_json_online(url),
+        FetchMode::Online | FetchMode::Record(_) => {}
+    }
+
+    match get_json_online(url) {
+        Ok(result) => Ok(result),
+        Err(err) => match read_from_cache(url, Some("stale")) {
+            Ok(result) => Ok(result),
+            Err(_) => Err(err),
+        },
+    }
+}
+
+/// Same as `get_json`, but never falls back to a cached response.
+///
+/// Successful responses are still cached, and replays are still served from their capture.
+pub fn get_json_online<T: DeserializeOwned>(url: &str) -> Result<T> {
+    let capture = match mode() {
+        FetchMode::Online => None,
+        FetchMode::Offline | FetchMode::Cached => return Err(anyhow!("Cannot fetch {} while offline", url)),
+        FetchMode::Record(capture) => Some(capture),
+        FetchMode::Replay(capture) => return Ok(serde_json::from_str(&capture.get(url)?)?),
+    };
+
+    let body = agent()
+        .get(url)
+        .call()
+        .and_then(|mut response| response.body_mut().read_to_string())?;
+    // Recorded before parsing, so responses that trip up the parsers can be replayed too
+    if let Some(capture) = capture {
+        capture.record(url, &body)?;
+    }
+    let result: T = serde_json::from_str(&body).with_context(|| format!("Unexpected response from {}", url))?;
+    // Failing to cache should never fail the actual request
+    let _ = save_to_cache(url, &body);
+    Ok(result)
+}
+
+fn read_from_cache<T: DeserializeOwned>(url: &str, label: Option<&str>) -> Result<T> {
+    let cached = envelope::read::<String, _>(&cache_path(url)?, RESPONSE_FORMAT_VERSION, |version, _| {
+        Err(anyhow!("Unknown response cache version {:?}", version))
+    })?;
+    let result: T = serde_json::from_str(&cached.payload)?;
+
+    // Several responses can fall back in one run (e.g, arrivals, alerts and elevators)
+    if let Some(label) = label {
+        let endpoint = Url::parse(url).map_or(url.to_owned(), |url| url.path().to_owned());
+        eprintln!(
+            "{}",
+            format!("{}: {} fetched {} ago", label, endpoint, format_age(cached.age())).yellow()
+        );
+    }
+    Ok(result)
+}
+
+fn save_to_cache(url: &str, body: &str) -> Result<()> {
+    envelope::write(&cache_path(url)?, RESPONSE_FORMAT_VERSION, url, &body)
+}
+
+fn cache_path(url: &str) -> Result<PathBuf> {
+    Ok(responses_dir()?.join(format!("{:016x}", hash_key(url))))
+}
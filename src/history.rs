@@ -0,0 +1,82 @@
+use crate::cache::data_dir;
+use crate::envelope;
+use crate::traits::{Parse, PrettyPrint};
+use anyhow::{Result, anyhow};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Version of the history payload, bumped whenever `History` changes shape.
+const HISTORY_FORMAT_VERSION: u32 = 1;
+
+/// How many times each station has been looked up, keyed by station parameter.
+#[derive(Serialize, Deserialize, Default)]
+pub struct History(HashMap<String, u32>);
+
+impl History {
+    /// Loads the history from disk, starting fresh if there is none or it can't be read.
+    pub fn load() -> Self {
+        Self::read_from_file().unwrap_or_default()
+    }
+
+    pub fn count(&self, parameter: &str) -> u32 {
+        self.0.get(parameter).copied().unwrap_or(0)
+    }
+
+    /// Records a lookup of the given station and saves the history.
+    pub fn record(&mut self, parameter: &str) -> Result<()> {
+        *self.0.entry(parameter.to_owned()).or_insert(0) += 1;
+        self.save()
+    }
+
+    /// Forgets every lookup.
+    pub fn reset() -> Result<()> {
+        let path = history_path()?;
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// Saves the history, atomically since it's rewritten on every lookup and runs may overlap.
+    fn save(&self) -> Result<()> {
+        envelope::write(&history_path()?, HISTORY_FORMAT_VERSION, "station lookups", self)
+    }
+
+    fn read_from_file() -> Result<Self> {
+        let history = envelope::read(&history_path()?, HISTORY_FORMAT_VERSION, |version, _| {
+            Err(anyhow!("Unknown history version {:?}", version))
+        })?;
+        Ok(history.payload)
+    }
+}
+
+fn history_path() -> Result<PathBuf> {
+    Ok(data_dir()?.join("history"))
+}
+
+impl Parse for History {
+    fn parse(&self) -> Vec<String> {
+        let mut entries: Vec<(&String, &u32)> = self.0.iter().collect();
+        entries.sort_by(|(a_name, a_count), (b_name, b_count)| b_count.cmp(a_count).then(a_name.cmp(b_name)));
+        entries
+            .into_iter()
+            .map(|(station, count)| format!("{:<8}{}", count, station))
+            .collect()
+    }
+}
+
+impl PrettyPrint for History {
+    fn print(&self) {
+        if self.0.is_empty() {
+            println!("{}", "No station lookups recorded yet".yellow());
+            return;
+        }
+        println!("{:<8}{}", "Count".cyan(), "Station".yellow());
+        for entry in self.parse().iter() {
+            println!("{entry}");
+        }
+    }
+}
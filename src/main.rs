@@ -1,21 +1,54 @@
+mod aliases;
+mod cache;
+mod capture;
+mod completion;
+mod envelope;
+mod fetch;
+mod history;
+mod matching;
+mod mock;
+mod registry;
+mod routes;
 mod septa;
 mod septum;
+mod service;
 mod stations;
 mod traits;
 mod utils;
 
-use crate::septa::{Arrivals, NextToArrive, TrainSchedule};
-use crate::septum::{ScheduleDirection, ScheduleMode, ScheduleOuter, SeptumMisc};
-use crate::stations::StationsManager;
+use crate::cache::CacheInfo;
+use crate::capture::Capture;
+use crate::fetch::FetchMode;
+use crate::history::History;
+use crate::mock::Fixtures;
+use crate::routes::ROUTES;
+use crate::septa::{
+    Alerts, Arrivals, Coordinates, Elevators, NearbyStations, NextToArrive, StopPredictions, Stops, TrainSchedule,
+    TrainView, TransitView,
+};
+use crate::septum::{ScheduleDirection, ScheduleMode, ScheduleOuter, ScheduleWindow, SeptumMisc, Trip};
+use crate::service::ServiceTime;
+use crate::stations::{Station, StationsManager};
 use crate::traits::{PrettyPrint, PrettyPrintWithMode};
-use anyhow::{Context, Result};
-use clap::{CommandFactory, Parser, Subcommand};
-use clap_complete::{generate, Shell};
+use anyhow::{Context, Result, anyhow};
+use clap::builder::PossibleValuesParser;
+use clap::{Parser, Subcommand};
+use clap_complete::env::Shells;
+use clap_complete::{ArgValueCandidates, CompleteEnv, Shell};
 use colored::Colorize;
+use jiff::civil::Date;
+use std::env;
 use std::io;
+use std::path::PathBuf;
+use std::thread::{self, JoinHandle};
 
 pub const URL: &str = "https://www3.septa.org/api";
 
+/// The SEPTA API base URL, can be overridden with `SeptaURL` (e.g, to point at `tst dev serve`)
+pub fn septa_url() -> String {
+    env::var("SeptaURL").unwrap_or_else(|_| URL.to_owned())
+}
+
 #[derive(Parser)]
 #[command(name = "tst")]
 #[command(about, version, author)]
@@ -24,17 +57,39 @@ pub const URL: &str = "https://www3.septa.org/api";
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Never touch the network, only use previously cached responses
+    #[arg(long, global = true, group = "fetch")]
+    offline: bool,
+
+    /// Disable fuzzy matching, station names have to match exactly
+    #[arg(long, global = true)]
+    exact: bool,
+
+    /// Store every raw API response in the given directory
+    #[arg(long, global = true, group = "fetch", value_name = "DIR")]
+    record: Option<PathBuf>,
+
+    /// Serve API responses from a directory created with `--record` instead of the network
+    #[arg(long, global = true, group = "fetch", value_name = "DIR")]
+    replay: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Search for the next train going from an origin to a destination
     Next {
-        /// Starting station
-        from: String,
+        /// Starting station, or the destination station when using `--near`
+        #[arg(required_unless_present = "near", add = ArgValueCandidates::new(completion::stations))]
+        from: Option<String>,
 
         /// Destination station
-        to: String,
+        #[arg(required_unless_present = "near", add = ArgValueCandidates::new(completion::stations))]
+        to: Option<String>,
+
+        /// Start from the station closest to these coordinates (e.g, 39.95,-75.16)
+        #[arg(long, value_name = "LAT,LON", allow_hyphen_values = true)]
+        near: Option<Coordinates>,
 
         /// Number of results to return
         #[arg(long, short, default_value = "5")]
@@ -44,7 +99,26 @@ enum Commands {
     /// Find the next arrivals at a given train station
     Arrivals {
         /// Station name
-        station: String,
+        #[arg(required_unless_present = "near", add = ArgValueCandidates::new(completion::stations))]
+        station: Option<String>,
+
+        /// Use the station closest to these coordinates (e.g, 39.95,-75.16)
+        #[arg(long, value_name = "LAT,LON", allow_hyphen_values = true, conflicts_with = "station")]
+        near: Option<Coordinates>,
+
+        /// Number of results to return
+        #[arg(long, short, default_value = "5")]
+        count: u8,
+    },
+
+    /// Find the train stations closest to a given location
+    #[command(allow_negative_numbers = true)]
+    Near {
+        /// Latitude (e.g, 39.95)
+        lat: f64,
+
+        /// Longitude (e.g, -75.16)
+        lon: f64,
 
         /// Number of results to return
         #[arg(long, short, default_value = "5")]
@@ -57,8 +131,66 @@ enum Commands {
         number: String,
     },
 
+    /// List all Regional Rail trains that are currently running
+    Trains {
+        /// Only show trains on lines matching this name (e.g, Paoli)
+        #[arg(long, short)]
+        line: Option<String>,
+
+        /// Sort trains by how late they are, latest first
+        #[arg(long)]
+        by_delay: bool,
+    },
+
+    /// Track buses and trolleys on a given route
+    Bus {
+        /// Bus or trolley route (e.g, 17)
+        #[arg(value_parser = PossibleValuesParser::new(ROUTES), ignore_case = true, hide_possible_values = true)]
+        route: String,
+    },
+
+    /// Find upcoming buses and trolleys at a given stop
+    Stop {
+        /// Bus or trolley route (e.g, 17)
+        #[arg(value_parser = PossibleValuesParser::new(ROUTES), ignore_case = true, hide_possible_values = true)]
+        route: String,
+
+        /// Stop name (e.g, 20th Market)
+        stop: String,
+
+        /// Number of results to return for each side of the street
+        #[arg(long, short, default_value = "5")]
+        count: u8,
+    },
+
+    /// Get service alerts and advisories for Regional Rail lines
+    Alerts {
+        /// Line name (e.g, Trenton), all lines with active alerts are shown if omitted
+        line: Option<String>,
+    },
+
+    /// Get elevator and escalator outages
+    Elevators {
+        /// Station name, all outages are shown if omitted
+        #[arg(add = ArgValueCandidates::new(completion::stations))]
+        station: Option<String>,
+    },
+
     /// Get all valid station names
-    Stations,
+    Stations {
+        /// Fetch the stations from Septum again, even if the cache is still fresh
+        #[arg(long)]
+        refresh: bool,
+
+        #[command(subcommand)]
+        command: Option<StationsCommands>,
+    },
+
+    /// Inspect or clear the cache
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
 
     /// All of the extra endpoints added by Septum
     Extra {
@@ -66,41 +198,145 @@ enum Commands {
         command: ExtraCommands,
     },
 
-    /// Generate shell completions
+    /// Tools for developing and testing tst
+    Dev {
+        #[command(subcommand)]
+        command: DevCommands,
+    },
+
+    /// Generate shell completions, including station names and line codes
     Completion {
         /// The shell to generate completions for
         shell: Shell,
     },
 }
 
+#[derive(Subcommand)]
+enum StationsCommands {
+    /// Show the best matching stations for a search, with their scores
+    Search {
+        /// Station name to search for
+        query: String,
+
+        /// Number of results to return
+        #[arg(long, short, default_value = "5")]
+        count: u8,
+
+        /// Only include stations on this line (e.g, TRE)
+        #[arg(long, add = ArgValueCandidates::new(completion::lines))]
+        line: Option<String>,
+
+        /// Only include stations in this fare zone (e.g, 3)
+        #[arg(long)]
+        zone: Option<String>,
+    },
+
+    /// Show how often each station has been looked up, used to rank ambiguous matches
+    History {
+        /// Forget every lookup
+        #[arg(long)]
+        reset: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheCommands {
+    /// Show where the cache lives, how big and how old it is
+    Info,
+
+    /// Clear the cache, everything is cleared if no flags are given
+    Clear {
+        /// Only clear the station cache
+        #[arg(long)]
+        stations: bool,
+
+        /// Only clear the cached Septum lines and per-line stations
+        #[arg(long)]
+        septum: bool,
+
+        /// Only clear the cached responses used as a fallback when offline
+        #[arg(long)]
+        responses: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum DevCommands {
+    /// Serve the SEPTA and Septum endpoints from fixtures on localhost
+    Serve {
+        /// Port to listen on
+        #[arg(long, short, default_value = "8080")]
+        port: u16,
+
+        /// Directory created with `--record` to serve instead of the bundled fixtures
+        #[arg(long, value_name = "DIR")]
+        fixtures: Option<PathBuf>,
+    },
+}
+
 #[derive(Subcommand)]
 enum ExtraCommands {
     /// Get Schedule from one station to another on a given line
     Schedule {
-        /// The Regional Rail Line Code (e.g, TRE)
-        line: String,
+        /// The line (e.g, TRE or Trenton), or the starting station to infer the line from the stations
+        #[arg(value_name = "LINE|ORIG", add = ArgValueCandidates::new(completion::schedule_args))]
+        first: String,
 
-        /// Starting Station (e.g, Trenton)
-        orig: String,
+        /// The starting station (e.g, Trenton), or the ending station if the line was left out
+        #[arg(value_name = "ORIG|DEST", add = ArgValueCandidates::new(completion::schedule_args))]
+        second: String,
 
-        /// Ending Station (e.g, Gray 30th Street)
-        dest: String,
+        /// The ending station (e.g, Gray 30th Street)
+        #[arg(value_name = "DEST", add = ArgValueCandidates::new(completion::schedule_args))]
+        third: Option<String>,
 
-        /// Trains going inbound
+        /// Trains going inbound, inferred from the station order if neither direction is given
         #[arg(long, group = "direction")]
         inbound: bool,
 
-        /// Trains going outbound
+        /// Trains going outbound, inferred from the station order if neither direction is given
         #[arg(long, group = "direction")]
         outbound: bool,
 
-        /// Weekday trains
+        /// Weekday trains, picked from the date if neither is given
         #[arg(long, group = "week")]
         weekday: bool,
 
-        /// Weekend trains
+        /// Weekend trains, also run on holidays
         #[arg(long, group = "week")]
         weekend: bool,
+
+        /// Date to get the schedule for, defaults to today in Philadelphia
+        #[arg(long, value_name = "YYYY-MM-DD")]
+        date: Option<Date>,
+
+        /// Only trains departing at or after this time (e.g, 17:00)
+        #[arg(long, value_name = "HH:MM", group = "start")]
+        after: Option<ServiceTime>,
+
+        /// Only trains departing at or before this time, trains after midnight can be given as 24:30
+        #[arg(long, value_name = "HH:MM")]
+        before: Option<ServiceTime>,
+
+        /// Only trains departing from now on
+        #[arg(long, group = "start", conflicts_with = "date")]
+        from_now: bool,
+
+        /// Show at most this many trains
+        #[arg(long, value_name = "N")]
+        limit: Option<usize>,
+
+        /// Pick the latest train arriving at the destination by this time
+        #[arg(long, value_name = "HH:MM", group = "trip", conflicts_with = "limit")]
+        arrive_by: Option<ServiceTime>,
+
+        /// Pick the earliest train departing from the origin at or after this time
+        #[arg(long, value_name = "HH:MM", groups = ["trip", "start"], conflicts_with = "limit")]
+        depart_after: Option<ServiceTime>,
+
+        /// Minutes of walking to the origin or from the destination, to leave room for
+        #[arg(long, value_name = "MINUTES", requires = "trip")]
+        buffer: Option<u32>,
     },
 
     /// Get all of the lines supported by the extra schedules endpoint
@@ -109,6 +345,7 @@ enum ExtraCommands {
     /// Get all of the stations for a given line
     Stations {
         /// The Regional Rail Line Code (e.g, TRE)
+        #[arg(add = ArgValueCandidates::new(completion::lines))]
         line: String,
 
         /// Trains going inbound
@@ -122,82 +359,317 @@ enum ExtraCommands {
 }
 
 fn main() {
+    CompleteEnv::with_factory(completion::command).complete();
     if let Err(e) = run() {
-        eprintln!("{} {}", "error:".red().bold(), e);
+        eprintln!("{} {:#}", "error:".red().bold(), e);
         std::process::exit(1);
     }
 }
 
 fn run() -> Result<()> {
-    let mut stations = StationsManager::new();
     let cli = Cli::parse();
+    let mode = match (cli.offline, cli.record, cli.replay) {
+        (true, _, _) => FetchMode::Offline,
+        (_, Some(dir), _) => FetchMode::Record(Capture::create(&dir)?),
+        (_, _, Some(dir)) => FetchMode::Replay(Capture::open(&dir)?),
+        (_, _, _) => FetchMode::Online,
+    };
+    fetch::set_mode(mode)?;
+    matching::set_exact(cli.exact);
 
     match cli.command {
-        Commands::Next { from, to, count } => {
-            let matching_from = stations
-                .fuzzy_search(&from)
-                .context("Invalid station, please use `tst stations` for all valid station names")?;
+        Commands::Next { from, to, near, count } => {
+            let mut stations = StationsManager::new();
+            let (matching_from, to) = match (near, from, to) {
+                (Some(coordinates), Some(to), None) => (nearest_station(&stations, &coordinates)?, to),
+                (None, Some(from), Some(to)) => (
+                    stations
+                        .fuzzy_search(&from)
+                        .context("Invalid station, please use `tst stations` for all valid station names")?,
+                    to,
+                ),
+                (Some(_), _, _) => return Err(anyhow!("Only the destination station can be given with `--near`")),
+                (None, _, _) => return Err(anyhow!("Both a starting and a destination station are required")),
+            };
             let matching_to = stations
                 .fuzzy_search(&to)
                 .context("Invalid station, please use `tst stations` for all valid station names")?;
-            let result = NextToArrive::get(&matching_from, &matching_to, count).context("Failed to get next trains")?;
+            let banners = Banners::fetch();
+            let result = NextToArrive::get(&matching_from.parameter, &matching_to.parameter, count)
+                .context("Failed to get next trains")?;
+            banners.print(&result.lines(), &[&matching_from, &matching_to]);
             result.print();
         }
-        Commands::Arrivals { station, count } => {
-            let matching_station = stations
-                .fuzzy_search(&station)
-                .context("Invalid station, please use `tst stations` for all valid station names")?;
-            let result = Arrivals::get(&matching_station, count).context("Failed to get arrivals")?;
+        Commands::Arrivals { station, near, count } => {
+            let mut stations = StationsManager::new();
+            let matching_station = match (near, station) {
+                (Some(coordinates), _) => nearest_station(&stations, &coordinates)?,
+                (None, Some(station)) => stations
+                    .fuzzy_search(&station)
+                    .context("Invalid station, please use `tst stations` for all valid station names")?,
+                (None, None) => return Err(anyhow!("A station is required")),
+            };
+            let banners = Banners::fetch();
+            let result = Arrivals::get(&matching_station.parameter, count).context("Failed to get arrivals")?;
+            banners.print(&result.lines(), &[&matching_station]);
+            result.print();
+        }
+        Commands::Near { lat, lon, count } => {
+            let result =
+                NearbyStations::get(&Coordinates { lat, lon }, count).context("Failed to get nearby stations")?;
             result.print();
         }
         Commands::Train { number } => {
             let result = TrainSchedule::get(&number).context("Failed to get train schedule")?;
 
             result.print();
+
+            // The live position is a nice to have, the schedule alone is still useful
+            if let Some(train) = TrainView::get().ok().and_then(|trains| trains.find(&number)) {
+                println!();
+                train.print();
+            }
         }
-        Commands::Stations => {
-            for station in stations.get_stations().iter() {
-                println!("{station}");
+        Commands::Trains { line, by_delay } => {
+            let mut result = TrainView::get().context("Failed to get trains")?;
+            if let Some(line) = line {
+                result.filter_line(&line);
+            }
+            if by_delay {
+                result.sort_by_delay();
             }
+            result.print();
+        }
+        Commands::Bus { route } => {
+            let result = TransitView::get(&route.to_uppercase()).context("Failed to get vehicles for route")?;
+            result.print();
+        }
+        Commands::Stop { route, stop, count } => {
+            let route = route.to_uppercase();
+            let matching_stops = Stops::get(&route)
+                .context("Failed to get stops for route")?
+                .fuzzy_search(&stop)
+                .context("Invalid stop, no stop on this route matches that name")?;
+            let result = StopPredictions::get(&route, &matching_stops, count).context("Failed to get predictions")?;
+            result.print();
+        }
+        Commands::Alerts { line } => {
+            let mut result = Alerts::get().context("Failed to get alerts")?;
+            if let Some(line) = line {
+                result = result
+                    .for_line(&line)
+                    .context("Invalid line, please use `tst alerts` to see all lines with alerts")?;
+            }
+            result.print();
         }
+        Commands::Elevators { station } => {
+            let mut result = Elevators::get().context("Failed to get elevator outages")?;
+            if let Some(station) = station {
+                let matching_station = StationsManager::new()
+                    .fuzzy_search(&station)
+                    .context("Invalid station, please use `tst stations` for all valid station names")?;
+                result = result.at_station(&matching_station);
+            }
+            result.print();
+        }
+        Commands::Cache { command } => match command {
+            CacheCommands::Info => {
+                let result = CacheInfo::get().context("Failed to read the cache")?;
+                result.print();
+            }
+            CacheCommands::Clear {
+                stations: clear_stations,
+                septum: clear_septum,
+                responses: clear_responses,
+            } => {
+                let everything = !clear_stations && !clear_septum && !clear_responses;
+                if clear_stations || everything {
+                    cache::clear_stations().context("Failed to clear the station cache")?;
+                }
+                if clear_septum || everything {
+                    cache::clear_septum().context("Failed to clear the Septum cache")?;
+                }
+                if clear_responses || everything {
+                    cache::clear_responses().context("Failed to clear cached responses")?;
+                }
+                println!("{}", "Cache cleared".green());
+            }
+        },
+        Commands::Stations { refresh, command } => match command {
+            Some(StationsCommands::Search {
+                query,
+                count,
+                line,
+                zone,
+            }) => {
+                let mut manager = load_stations(refresh)?;
+                // Lines come from Septum, which is only required when filtering by line
+                let lines = SeptumMisc::new().and_then(|septum| manager.link_lines(&septum));
+                if line.is_some() {
+                    lines.context("Failed to get the stations on each line from Septum")?;
+                }
+                let mut result = manager.search(&query, line.as_deref(), zone.as_deref());
+                result.0.truncate(count as usize);
+                result.print();
+            }
+            Some(StationsCommands::History { reset }) => {
+                if refresh {
+                    load_stations(refresh)?;
+                }
+                if reset {
+                    History::reset().context("Failed to reset station history")?;
+                    println!("{}", "Station history cleared".green());
+                } else {
+                    History::load().print();
+                }
+            }
+            None => {
+                for station in load_stations(refresh)?.get_stations().iter() {
+                    println!("{station}");
+                }
+            }
+        },
+        Commands::Dev { command } => match command {
+            DevCommands::Serve { port, fixtures } => {
+                let fixtures = match fixtures {
+                    Some(dir) => Fixtures::Capture(Capture::open(&dir)?),
+                    None => Fixtures::Bundled,
+                };
+                mock::serve(port, fixtures)?;
+            }
+        },
         Commands::Completion { shell } => {
-            let mut cmd = Cli::command();
-            generate(shell, &mut cmd, "tst", &mut io::stdout());
+            // The script calls back into `tst` for candidates, see `completion`
+            let shells = Shells::builtins();
+            let completer = shells
+                .completer(&shell.to_string())
+                .ok_or(anyhow!("Completions are not supported for {}", shell))?;
+            completer.write_registration("COMPLETE", "tst", "tst", "tst", &mut io::stdout())?;
         }
         Commands::Extra { command } => {
-            let mut manager = SeptumMisc::new()?;
+            let manager = SeptumMisc::new()?;
 
             match command {
                 ExtraCommands::Schedule {
-                    line,
-                    orig,
-                    dest,
+                    first,
+                    second,
+                    third,
                     inbound,
                     outbound,
                     weekday,
                     weekend,
+                    date,
+                    after,
+                    before,
+                    from_now,
+                    limit,
+                    arrive_by,
+                    depart_after,
+                    buffer,
                 } => {
                     let direction = match (inbound, outbound) {
-                        (true, _) => ScheduleDirection::Inbound,
-                        (_, true) => ScheduleDirection::Outbound,
-                        (_, _) => ScheduleDirection::Inbound,
+                        (true, _) => Some(ScheduleDirection::Inbound),
+                        (_, true) => Some(ScheduleDirection::Outbound),
+                        (_, _) => None,
                     };
 
                     let mode = match (weekday, weekend) {
                         (true, _) => ScheduleMode::Weekday,
                         (_, true) => ScheduleMode::Weekend,
-                        (_, _) => ScheduleMode::Weekday,
+                        (_, _) => {
+                            let day = match date {
+                                Some(day) => day,
+                                None => service::today()?,
+                            };
+                            let (mode, holiday) = service::service_for(day);
+                            if let Some(holiday) = holiday {
+                                let service = match mode {
+                                    ScheduleMode::Weekday => "weekday",
+                                    ScheduleMode::Weekend => "weekend",
+                                };
+                                println!("{} {} on {} ({})", "Service:".green(), service, day, holiday);
+                            }
+                            mode
+                        }
+                    };
+
+                    // Both directions share stop ids, so either list works for matching
+                    let listed = direction.as_ref().unwrap_or(&ScheduleDirection::Inbound);
+                    let mut stations = StationsManager::new();
+                    let (line, matching_orig, matching_dest) = match (first, second, third) {
+                        (line, orig, Some(dest)) => {
+                            let line = manager
+                                .match_line(&line)
+                                .context("Invalid line, please use `tst extra lines` for all valid lines")?
+                                .line_code;
+                            let stops = manager.get_stations_for_line(&line, listed).context(
+                                "An error occurred while getting station, please check your Septum URL and inputs",
+                            )?;
+                            let matching_orig = stations
+                                .fuzzy_search_stop(&stops, &orig)
+                                .context("Invalid station, please use `tst extra stations [LINE]` for all valid station names for a given line")?;
+                            let matching_dest = stations
+                                .fuzzy_search_stop(&stops, &dest)
+                                .context("Invalid station, please use `tst extra stations [LINE]` for all valid station names for a given line")?;
+                            (line, matching_orig, matching_dest)
+                        }
+                        (orig, dest, None) => {
+                            let (line, matching_orig, matching_dest) =
+                                stations.infer_line(&manager, &orig, &dest, listed)?;
+                            println!("{} {:<6}{}", "Line:".green(), line.line_code, line.line_name);
+                            (line.line_code, matching_orig, matching_dest)
+                        }
+                    };
+                    let direction = match direction {
+                        Some(direction) => direction,
+                        None => manager
+                            .infer_direction(&line, &matching_orig.stop_id, &matching_dest.stop_id)?
+                            .context("Unable to tell the direction from the station order, please use `--inbound` or `--outbound`")?,
                     };
+                    let mut result = ScheduleOuter::get(
+                        &line,
+                        &direction,
+                        &matching_orig.stop_name,
+                        &matching_dest.stop_name,
+                    )
+                    .context(
+                        "An error occurred while getting train schedule, please check your Septum URL and inputs",
+                    )?;
+                    let after = match from_now {
+                        true => {
+                            // Trains running after midnight belong to yesterday's service, and may not have left yet
+                            let previous = match (weekday, weekend) {
+                                (true, _) => ScheduleMode::Weekday,
+                                (_, true) => ScheduleMode::Weekend,
+                                (_, _) => service::service_for(service::today()?.yesterday()?).0,
+                            };
+                            result.carry_over(&previous, &mode);
+                            Some(ServiceTime::now()?)
+                        }
+                        false => after,
+                    };
+                    result.filter(&ScheduleWindow { after, before, limit });
 
-                    let matching_orig = manager
-                        .fuzzy_match_station_for_line(&line, &orig, &direction)
-                        .context("Invalid station, please use `tst extra stations [LINE]` for all valid station names for a given line")?;
-                    let matching_dest = manager
-                        .fuzzy_match_station_for_line(&line, &dest, &direction)
-                        .context("Invalid station, please use `tst extra stations [LINE]` for all valid station names for a given line")?;
-                    let result = ScheduleOuter::get(&line, &direction, &matching_orig, &matching_dest)
-                        .context("An error occurred while getting train schedule, please double check the direction")?;
-                    result.print(&mode);
+                    let buffer = buffer.unwrap_or(0);
+                    let trip = match (arrive_by, depart_after) {
+                        (Some(by), _) => Some(Trip::ArriveBy(by.earlier(buffer).ok_or(anyhow!(
+                            "A {} minute buffer before {} goes back past midnight",
+                            buffer,
+                            by
+                        ))?)),
+                        (_, Some(after)) => Some(Trip::DepartAfter(after.later(buffer))),
+                        (_, _) => None,
+                    };
+                    match trip {
+                        Some(trip) => {
+                            let chosen = result.plan(&mode, trip).ok_or(match trip {
+                                Trip::ArriveBy(by) => anyhow!("No train arrives by {}", by),
+                                Trip::DepartAfter(after) => anyhow!("No train departs at or after {}", after),
+                            })?;
+                            result.print_plan(&mode, chosen);
+                        }
+                        None => result.print(&mode),
+                    }
                 }
                 ExtraCommands::Lines => {
                     let result = manager
@@ -219,7 +691,7 @@ fn run() -> Result<()> {
                         .get_stations_for_line(&line, &direction)
                         .context("An error occurred while getting station, please check your Septum URL and inputs")?;
                     for station in stations.iter() {
-                        println!("{station}");
+                        println!("{}", station.stop_name);
                     }
                 }
             }
@@ -227,3 +699,59 @@ fn run() -> Result<()> {
     }
     Ok(())
 }
+
+/// Alerts and elevator outages, fetched in the background while the actual request runs.
+struct Banners {
+    alerts: JoinHandle<Result<Alerts>>,
+    elevators: JoinHandle<Result<Elevators>>,
+}
+
+impl Banners {
+    fn fetch() -> Self {
+        Banners {
+            alerts: thread::spawn(Alerts::get),
+            elevators: thread::spawn(Elevators::get),
+        }
+    }
+
+    /// Prints the banners relevant to the given lines and stations.
+    ///
+    /// Banners are a nice to have, they should never get in the way of the actual results.
+    fn print(self, lines: &[String], stations: &[&Station]) {
+        if let Ok(Ok(alerts)) = self.alerts.join() {
+            alerts.print_banner(lines);
+        }
+        if let Ok(Ok(elevators)) = self.elevators.join() {
+            elevators.print_warning(stations);
+        }
+    }
+}
+
+/// Loads the stations for matching, fetching them from Septum first if `refresh` is set.
+fn load_stations(refresh: bool) -> Result<StationsManager> {
+    if !refresh {
+        return Ok(StationsManager::new());
+    }
+    let stations = StationsManager::refreshed().context("Failed to refresh stations from Septum")?;
+    eprintln!(
+        "{}",
+        format!("Refreshed {} stations", stations.get_stations().len()).green()
+    );
+    Ok(stations)
+}
+
+/// Finds the station closest to the given coordinates, as a valid station name.
+fn nearest_station(stations: &StationsManager, coordinates: &Coordinates) -> Result<Station> {
+    let nearest = NearbyStations::nearest(coordinates)?;
+    // Not something the user searched for, so it shouldn't boost later searches
+    let matching_station = stations
+        .fuzzy_find(nearest.name())
+        .context("The nearest station is not a valid station name")?;
+    println!(
+        "{} {} ({})",
+        "Nearest station:".green(),
+        matching_station,
+        nearest.distance()
+    );
+    Ok(matching_station)
+}
@@ -0,0 +1,136 @@
+use anyhow::{Context, Result, anyhow};
+use colored::Colorize;
+use fuzzy_matcher::FuzzyMatcher;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static EXACT: AtomicBool = AtomicBool::new(false);
+
+/// Matches whose confidence falls below this are considered ambiguous.
+///
+/// Confidence is how far ahead of the runner up the best match is, relative to the best score,
+/// so 0.1 means the best match has to score at least 10% higher than the next one.
+const CONFIDENCE_THRESHOLD: f64 = 0.1;
+
+/// Maximum number of alternatives shown when a match is ambiguous.
+const MAX_ALTERNATIVES: usize = 5;
+
+/// Disable fuzzy matching entirely, names have to match exactly (ignoring case).
+pub fn set_exact(exact: bool) {
+    EXACT.store(exact, Ordering::Relaxed);
+}
+
+fn is_exact() -> bool {
+    EXACT.load(Ordering::Relaxed)
+}
+
+/// Scores every item for `search`, best first.
+///
+/// Each term is paired with the index of the item it belongs to, so an item can be matched by
+/// several names (e.g, aliases). Only the best scoring term counts for each item, and items
+/// that don't match at all are left out.
+pub fn rank<T: AsRef<str>>(terms: &[(T, usize)], search: &str) -> Vec<(usize, i64)> {
+    let matcher = SkimMatcherV2::default();
+    let mut scores: Vec<(usize, i64)> = Vec::new();
+
+    for (term, index) in terms {
+        let Some(score) = matcher.fuzzy_match(term.as_ref(), search) else {
+            continue;
+        };
+        match scores.iter_mut().find(|(existing, _)| existing == index) {
+            Some((_, best)) => *best = (*best).max(score),
+            None => scores.push((*index, score)),
+        }
+    }
+
+    scores.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+    scores
+}
+
+/// How far ahead of the runner up the best match is, from 0.0 (tied) to 1.0 (unchallenged).
+pub fn confidence(ranked: &[(usize, i64)]) -> f64 {
+    match ranked {
+        [] => 0.0,
+        [_] => 1.0,
+        [(_, best), (_, second), ..] if *best > 0 => (best - second) as f64 / *best as f64,
+        _ => 0.0,
+    }
+}
+
+/// Picks the item that best matches `search`, returning its index.
+///
+/// When the best candidates are too close to call, the user is asked to pick one on a TTY,
+/// otherwise the alternatives are printed and an error is returned instead of guessing.
+/// An item with a term equal to `search` (ignoring case) always wins, and with `--exact`
+/// those are the only items considered. `boost` adds to the score of each matching item,
+/// which helps break ties (e.g, in favor of frequently used stations).
+pub fn best_match<T: AsRef<str>>(
+    terms: &[(T, usize)],
+    labels: &[String],
+    search: &str,
+    boost: impl Fn(usize) -> i64,
+) -> Result<usize> {
+    // A name that matches exactly is never ambiguous, even if others contain it (e.g, West Trenton)
+    let exact = terms
+        .iter()
+        .find(|(term, _)| term.as_ref().trim().eq_ignore_ascii_case(search.trim()))
+        .map(|(_, index)| *index);
+    if is_exact() || exact.is_some() {
+        return exact.ok_or(anyhow!("Nothing named exactly '{}'", search));
+    }
+
+    let mut ranked = rank(terms, search);
+    for (index, score) in ranked.iter_mut() {
+        *score += boost(*index);
+    }
+    ranked.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+
+    let (best, _) = ranked.first().ok_or(anyhow!("Nothing matches '{}'", search))?;
+    if confidence(&ranked) >= CONFIDENCE_THRESHOLD {
+        return Ok(*best);
+    }
+
+    let alternatives: Vec<usize> = ranked.iter().take(MAX_ALTERNATIVES).map(|(index, _)| *index).collect();
+    let question = format!("'{}' is ambiguous, did you mean", search);
+    let hint = "Use a more specific name, or `--exact` to disable fuzzy matching";
+    choose(&alternatives, labels, &question, hint)?.ok_or(anyhow!("Ambiguous match for '{}'", search))
+}
+
+/// Asks the user to pick one of `choices` on a TTY, returning its index.
+///
+/// Otherwise the choices are printed along with `hint`, and `None` is returned.
+pub fn choose(choices: &[usize], labels: &[String], question: &str, hint: &str) -> Result<Option<usize>> {
+    if io::stdin().is_terminal() && io::stderr().is_terminal() {
+        return prompt(choices, labels, question).map(Some);
+    }
+
+    eprintln!("{}", format!("{} one of:", question).yellow());
+    for index in choices.iter() {
+        eprintln!("  {}", labels[*index]);
+    }
+    eprintln!("{}", hint.yellow());
+    Ok(None)
+}
+
+fn prompt(choices: &[usize], labels: &[String], question: &str) -> Result<usize> {
+    eprintln!("{}", format!("{}:", question).yellow());
+    for (number, index) in choices.iter().enumerate() {
+        eprintln!("  {}) {}", number + 1, labels[*index]);
+    }
+    eprint!("Pick one [1-{}, default 1]: ", choices.len());
+    io::stderr().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    let answer = answer.trim();
+    if answer.is_empty() {
+        return Ok(choices[0]);
+    }
+
+    let number: usize = answer.parse().context("Invalid choice")?;
+    choices
+        .get(number.wrapping_sub(1))
+        .copied()
+        .ok_or(anyhow!("Invalid choice"))
+}
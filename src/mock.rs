@@ -0,0 +1,106 @@
+use crate::capture::{Capture, find_entry};
+use anyhow::{Result, anyhow};
+use colored::Colorize;
+use tiny_http::{Header, Response, Server};
+
+const BUNDLED_INDEX: &str = include_str!("../fixtures/index");
+
+const BUNDLED_FIXTURES: [(&str, &str); 18] = [
+    ("arrivals.json", include_str!("../fixtures/arrivals.json")),
+    ("next_to_arrive.json", include_str!("../fixtures/next_to_arrive.json")),
+    ("rr_schedules.json", include_str!("../fixtures/rr_schedules.json")),
+    ("train_view.json", include_str!("../fixtures/train_view.json")),
+    ("alerts.json", include_str!("../fixtures/alerts.json")),
+    ("elevators.json", include_str!("../fixtures/elevators.json")),
+    ("transit_view.json", include_str!("../fixtures/transit_view.json")),
+    ("stops.json", include_str!("../fixtures/stops.json")),
+    ("bus_schedules.json", include_str!("../fixtures/bus_schedules.json")),
+    ("locations.json", include_str!("../fixtures/locations.json")),
+    ("stations.json", include_str!("../fixtures/stations.json")),
+    ("schedule.json", include_str!("../fixtures/schedule.json")),
+    ("schedule_lines.json", include_str!("../fixtures/schedule_lines.json")),
+    (
+        "schedule_stations_tre_inbound.json",
+        include_str!("../fixtures/schedule_stations_tre_inbound.json"),
+    ),
+    (
+        "schedule_stations_tre_outbound.json",
+        include_str!("../fixtures/schedule_stations_tre_outbound.json"),
+    ),
+    (
+        "schedule_stations_pao_inbound.json",
+        include_str!("../fixtures/schedule_stations_pao_inbound.json"),
+    ),
+    (
+        "schedule_stations_pao_outbound.json",
+        include_str!("../fixtures/schedule_stations_pao_outbound.json"),
+    ),
+    (
+        "schedule_stations_other.json",
+        include_str!("../fixtures/schedule_stations_other.json"),
+    ),
+];
+
+/// Where the stand-in server gets its responses from.
+pub enum Fixtures {
+    /// The fixtures compiled into `tst`
+    Bundled,
+    /// A directory created with `--record`
+    Capture(Capture),
+}
+
+impl Fixtures {
+    fn lookup(&self, key: &str) -> Result<String> {
+        match self {
+            Fixtures::Bundled => {
+                let entries: Vec<(String, &str)> = BUNDLED_INDEX
+                    .lines()
+                    .filter_map(|line| line.split_once('\t'))
+                    .map(|(file, key)| (key.to_owned(), file))
+                    .collect();
+                let file = find_entry(&entries, key).ok_or(anyhow!("No fixture for {}", key))?;
+                BUNDLED_FIXTURES
+                    .iter()
+                    .find(|(name, _)| name == file)
+                    .map(|(_, body)| body.to_string())
+                    .ok_or(anyhow!("Fixture {} is missing", file))
+            }
+            Fixtures::Capture(capture) => capture.lookup(key),
+        }
+    }
+}
+
+/// Serves the SEPTA and Septum endpoints used by `tst` from fixtures on localhost.
+pub fn serve(port: u16, fixtures: Fixtures) -> Result<()> {
+    let server = Server::http(("127.0.0.1", port)).map_err(|e| anyhow!("Unable to start server: {}", e))?;
+    let content_type =
+        Header::from_bytes("Content-Type", "application/json").map_err(|_| anyhow!("Invalid Content-Type header"))?;
+
+    let base_url = format!("http://127.0.0.1:{}/api", port);
+    println!("Serving fixtures on {}", base_url.green());
+    println!("Point tst at it with:");
+    println!("  export SeptaURL=\"{}\"", base_url);
+    println!("  export SeptumURL=\"{}\"", base_url);
+
+    for request in server.incoming_requests() {
+        let key = request.url().to_owned();
+        let (status, body) = match fixtures.lookup(&key) {
+            Ok(body) => (200, body),
+            Err(e) => (404, serde_json::json!({ "error": e.to_string() }).to_string()),
+        };
+
+        let status_text = if status == 200 {
+            status.to_string().green()
+        } else {
+            status.to_string().red()
+        };
+        println!("{} {}", status_text, key);
+
+        let response = Response::from_string(body)
+            .with_status_code(status)
+            .with_header(content_type.clone());
+        // A client hanging up early should not bring the server down
+        let _ = request.respond(response);
+    }
+    Ok(())
+}
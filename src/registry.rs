@@ -0,0 +1,79 @@
+use crate::cache::registry_path;
+use crate::envelope;
+use crate::septum::LinesStationsInner;
+use crate::stations::Station;
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Version of the registry payload, bumped whenever `LinkedStop` changes shape.
+const REGISTRY_FORMAT_VERSION: u32 = 1;
+
+/// A Septum stop, linked to the SEPTA station it is.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct LinkedStop {
+    /// The name SEPTA's realtime endpoints expect (e.g, "30th Street Station")
+    pub parameter: String,
+    /// The name Septum's schedule endpoints expect (e.g, "Gray 30th Street")
+    pub stop_name: String,
+}
+
+/// Every Septum stop linked to a SEPTA station so far, keyed by Septum stop id.
+///
+/// Stops are linked when Septum's per-line station lists are seen, by stop id if the station
+/// records carry one and by name (aliases included) otherwise. Links are kept on disk, so a
+/// station resolved once can be used with SEPTA and Septum alike.
+#[derive(Default)]
+pub struct StationRegistry(HashMap<String, LinkedStop>);
+
+impl StationRegistry {
+    /// Loads the registry from disk, starting fresh if there is none or it can't be read.
+    pub fn load() -> Self {
+        Self::read_from_file().map(StationRegistry).unwrap_or_default()
+    }
+
+    pub fn links(&self) -> impl Iterator<Item = (&String, &LinkedStop)> {
+        self.0.iter()
+    }
+
+    /// Links each stop to the station it is, saving the registry if anything new was learned.
+    pub fn link(&mut self, stops: &[LinesStationsInner], stations: &[Station]) {
+        let mut changed = false;
+        for stop in stops {
+            let station = stations
+                .iter()
+                .find(|station| station.stop_ids.contains(&stop.stop_id))
+                .or_else(|| stations.iter().find(|station| station.is_named(&stop.stop_name)));
+            let Some(station) = station else {
+                continue;
+            };
+
+            let linked = LinkedStop {
+                parameter: station.parameter.clone(),
+                stop_name: stop.stop_name.clone(),
+            };
+            if self.0.get(&stop.stop_id) != Some(&linked) {
+                self.0.insert(stop.stop_id.clone(), linked);
+                changed = true;
+            }
+        }
+
+        // Failing to save only means linking the same stops again next time
+        if changed {
+            let _ = self.save();
+        }
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = registry_path()?;
+        envelope::write(&path, REGISTRY_FORMAT_VERSION, "Septum /schedule/stations", &self.0)
+    }
+
+    fn read_from_file() -> Result<HashMap<String, LinkedStop>> {
+        let path = registry_path()?;
+        let registry = envelope::read(&path, REGISTRY_FORMAT_VERSION, |version, _| {
+            Err(anyhow!("Unknown station registry version {:?}", version))
+        })?;
+        Ok(registry.payload)
+    }
+}
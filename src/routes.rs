@@ -0,0 +1,13 @@
+/// Every bus and trolley route supported by TransitView.
+///
+/// Used to validate route numbers before hitting the API, and for shell completion.
+pub const ROUTES: [&str; 141] = [
+    "1", "2", "3", "4", "5", "6", "7", "8", "9", "10", "11", "12", "13", "14", "15", "16", "17", "18", "19", "20",
+    "21", "22", "23", "24", "25", "26", "27", "28", "29", "30", "31", "32", "33", "34", "35", "36", "37", "38", "39",
+    "40", "42", "43", "44", "45", "46", "47", "47M", "48", "49", "50", "52", "53", "54", "55", "56", "57", "58", "59",
+    "60", "61", "62", "64", "65", "66", "67", "68", "70", "73", "75", "77", "78", "79", "80", "84", "88", "89", "90",
+    "91", "92", "93", "94", "95", "96", "97", "98", "99", "101", "102", "103", "104", "105", "106", "107", "108",
+    "109", "110", "111", "113", "114", "115", "117", "118", "119", "120", "123", "124", "125", "126", "127", "128",
+    "129", "130", "131", "132", "133", "135", "139", "150", "201", "204", "206", "310", "311", "312", "BLVDDIR", "G",
+    "H", "J", "K", "L", "LUCY", "R", "XH", "T1", "T2", "T3", "T4", "T5", "D1", "D2", "G1",
+];
@@ -0,0 +1,117 @@
+use crate::fetch::get_json;
+use crate::matching::best_match;
+use crate::septa_url;
+use crate::traits::{Parse, PrettyPrint};
+use crate::utils::strip_html;
+use anyhow::Result;
+use colored::Colorize;
+use serde::Deserialize;
+use url::Url;
+
+#[derive(Deserialize)]
+pub struct AlertsInner {
+    route_id: String,
+    route_name: String,
+    current_message: Option<String>,
+    advisory_message: Option<String>,
+    last_updated: Option<String>,
+}
+
+impl AlertsInner {
+    /// Non-empty messages for this route, with the markup SEPTA includes stripped out.
+    fn messages(&self) -> Vec<String> {
+        [self.current_message.as_deref(), self.advisory_message.as_deref()]
+            .into_iter()
+            .flatten()
+            .map(strip_html)
+            .filter(|message| !message.is_empty())
+            .collect()
+    }
+
+    fn is_active(&self) -> bool {
+        !self.messages().is_empty()
+    }
+}
+
+/// Service alerts and advisories for every Regional Rail line.
+#[derive(Deserialize)]
+pub struct Alerts(pub Vec<AlertsInner>);
+
+impl Alerts {
+    pub fn get() -> Result<Alerts> {
+        let request_url = Url::parse(&format!("{}/Alerts/index.php", septa_url()))?;
+        let result: Alerts = get_json(request_url.as_ref())?;
+
+        // The endpoint covers every mode, only Regional Rail is relevant here
+        Ok(Alerts(
+            result
+                .0
+                .into_iter()
+                .filter(|alert| alert.route_id.starts_with("rr_route_"))
+                .collect(),
+        ))
+    }
+
+    /// Keeps only the line that best matches `line` (e.g, "trenton" or "paoli thorndale").
+    pub fn for_line(self, line: &str) -> Result<Alerts> {
+        let mut names: Vec<String> = self.0.iter().map(|alert| alert.route_name.clone()).collect();
+        names.sort();
+        names.dedup();
+        let terms: Vec<(&String, usize)> = names.iter().zip(0..).collect();
+        let name = names[best_match(&terms, &names, line, |_| 0)?].clone();
+
+        Ok(Alerts(
+            self.0.into_iter().filter(|alert| alert.route_name == name).collect(),
+        ))
+    }
+
+    /// Prints a one-line banner if any of the given lines have active alerts.
+    pub fn print_banner(&self, lines: &[String]) {
+        let affected: Vec<&str> = self
+            .0
+            .iter()
+            .filter(|alert| alert.is_active())
+            .filter(|alert| lines.iter().any(|line| line.eq_ignore_ascii_case(&alert.route_name)))
+            .map(|alert| alert.route_name.as_str())
+            .collect();
+
+        if !affected.is_empty() {
+            println!(
+                "{} {} {}",
+                "Active alerts on".red().bold(),
+                affected.join(", ").red().bold(),
+                "(see `tst alerts [LINE]` for details)".red()
+            );
+        }
+    }
+}
+
+impl Parse for Alerts {
+    fn parse(&self) -> Vec<String> {
+        self.0
+            .iter()
+            .filter(|alert| alert.is_active())
+            .map(|alert| {
+                let mut lines = vec![format!(
+                    "{} {}",
+                    alert.route_name.yellow().bold(),
+                    format!("(updated {})", alert.last_updated.as_deref().unwrap_or("None")).blue()
+                )];
+                lines.extend(alert.messages().into_iter().map(|message| format!("  {}", message)));
+                lines.join("\n")
+            })
+            .collect()
+    }
+}
+
+impl PrettyPrint for Alerts {
+    fn print(&self) {
+        let alerts = self.parse();
+        if alerts.is_empty() {
+            println!("{}", "No active alerts".green());
+        }
+        for alert in alerts.iter() {
+            println!("{alert}\n");
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use crate::URL;
+use crate::fetch::get_json;
+use crate::septa_url;
 use crate::traits::{Parse, PrettyPrint};
 use crate::utils::parse_datetime;
 use anyhow::{Context, Result};
@@ -11,6 +12,7 @@ use url::Url;
 pub struct Train {
     train_id: Option<String>,
     destination: Option<String>,
+    line: Option<String>,
     status: Option<String>,
     next_station: Option<String>,
     sched_time: Option<String>,
@@ -29,9 +31,14 @@ pub struct Arrivals {
 
 impl Arrivals {
     pub fn get(name: &str, num: u8) -> Result<Arrivals> {
-        let request_url = Url::parse(&format!("{}/Arrivals/index.php?station={}&results={}", URL, name, num))?;
+        let request_url = Url::parse(&format!(
+            "{}/Arrivals/index.php?station={}&results={}",
+            septa_url(),
+            name,
+            num
+        ))?;
 
-        let response: ApiResponse = ureq::get(request_url.as_ref()).call()?.body_mut().read_json()?;
+        let response: ApiResponse = get_json(request_url.as_ref())?;
 
         Self::from_response(response)
     }
@@ -53,6 +60,19 @@ impl Arrivals {
 
         Ok(Arrivals { northbound, southbound })
     }
+
+    /// All of the distinct lines serving these arrivals.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .northbound
+            .iter()
+            .chain(self.southbound.iter())
+            .filter_map(|train| train.line.clone())
+            .collect();
+        lines.sort();
+        lines.dedup();
+        lines
+    }
 }
 
 impl Parse for Arrivals {
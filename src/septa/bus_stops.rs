@@ -0,0 +1,107 @@
+use crate::fetch::get_json;
+use crate::matching::best_match;
+use crate::septa_url;
+use crate::traits::{Parse, PrettyPrint};
+use anyhow::Result;
+use colored::Colorize;
+use serde::Deserialize;
+use std::collections::HashMap;
+use url::Url;
+
+#[derive(Deserialize, Clone)]
+pub struct Stop {
+    stopid: String,
+    stopname: String,
+}
+
+/// Every stop served by a given bus or trolley route.
+#[derive(Deserialize)]
+pub struct Stops(pub Vec<Stop>);
+
+impl Stops {
+    pub fn get(route: &str) -> Result<Stops> {
+        let request_url = Url::parse(&format!("{}/Stops/index.php?req1={}", septa_url(), route))?;
+        let result: Stops = get_json(request_url.as_ref())?;
+        Ok(result)
+    }
+
+    /// Returns every stop whose name best matches `search`.
+    ///
+    /// A stop usually has a separate id for each side of the street, so all of them are returned.
+    pub fn fuzzy_search(&self, search: &str) -> Result<Vec<Stop>> {
+        let mut names: Vec<String> = self.0.iter().map(|stop| stop.stopname.clone()).collect();
+        names.sort();
+        names.dedup();
+        let terms: Vec<(&String, usize)> = names.iter().zip(0..).collect();
+        let name = &names[best_match(&terms, &names, search, |_| 0)?];
+
+        Ok(self.0.iter().filter(|stop| &stop.stopname == name).cloned().collect())
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Prediction {
+    #[serde(rename = "DateCalender")]
+    date_calendar: Option<String>,
+    #[serde(rename = "DirectionDesc")]
+    direction_desc: Option<String>,
+}
+
+/// Upcoming vehicles at a given stop on a given route.
+pub struct StopPredictions {
+    pub stop: String,
+    pub predictions: Vec<Prediction>,
+}
+
+impl StopPredictions {
+    pub fn get(route: &str, stops: &[Stop], num: u8) -> Result<StopPredictions> {
+        let mut predictions = Vec::new();
+        for stop in stops {
+            let request_url = Url::parse(&format!(
+                "{}/BusSchedules/index.php?stop_id={}&req2={}",
+                septa_url(),
+                stop.stopid,
+                route
+            ))?;
+            // The API returns: `{ "route": [{...}, {...}] }`
+            let mut response: HashMap<String, Vec<Prediction>> = get_json(request_url.as_ref())?;
+            if let Some(upcoming) = response.remove(route) {
+                predictions.extend(upcoming.into_iter().take(num as usize));
+            }
+        }
+
+        Ok(StopPredictions {
+            stop: stops.first().map(|stop| stop.stopname.clone()).unwrap_or_default(),
+            predictions,
+        })
+    }
+}
+
+impl Parse for StopPredictions {
+    fn parse(&self) -> Vec<String> {
+        self.predictions
+            .iter()
+            .map(|prediction| {
+                format!(
+                    "{:<20}{}",
+                    prediction.date_calendar.as_deref().unwrap_or("None"),
+                    prediction.direction_desc.as_deref().unwrap_or("None"),
+                )
+            })
+            .collect()
+    }
+}
+
+impl PrettyPrint for StopPredictions {
+    fn print(&self) {
+        println!("{}", self.stop.green().bold());
+        if self.predictions.is_empty() {
+            println!("{}", "No upcoming vehicles at this stop".yellow());
+            return;
+        }
+        println!("{:<20}{}", "Time".magenta(), "Towards".yellow());
+        for prediction in self.parse().iter() {
+            println!("{prediction}");
+        }
+    }
+}
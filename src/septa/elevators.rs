@@ -0,0 +1,117 @@
+use crate::fetch::get_json;
+use crate::septa_url;
+use crate::stations::Station;
+use crate::traits::{Parse, PrettyPrint};
+use anyhow::Result;
+use colored::Colorize;
+use serde::Deserialize;
+use std::iter;
+use url::Url;
+
+#[derive(Deserialize)]
+pub struct ElevatorOutage {
+    line: Option<String>,
+    station: Option<String>,
+    elevator: Option<String>,
+    message: Option<String>,
+}
+
+/// The line SEPTA lists outages at Regional Rail stations under.
+const REGIONAL_RAIL: &str = "Regional Rail";
+
+impl ElevatorOutage {
+    /// Whether this outage is at the given Regional Rail station, by any name it goes by.
+    ///
+    /// Names are compared ignoring case and whitespace, but otherwise have to match exactly
+    /// since other lines have stations with similar or identical names (e.g, 30th St, Allegheny).
+    fn is_at(&self, station: &Station) -> bool {
+        let (Some(line), Some(outage_station)) = (self.line.as_deref(), self.station.as_deref()) else {
+            return false;
+        };
+        let outage_station = normalize(outage_station);
+        line == REGIONAL_RAIL
+            && iter::once(&station.parameter)
+                .chain(iter::once(&station.name))
+                .chain(station.aliases.iter())
+                .any(|name| normalize(name) == outage_station)
+    }
+}
+
+fn normalize(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<&str>>().join(" ").to_lowercase()
+}
+
+/// Intermediate structure matching the SEPTA API response format.
+#[derive(Deserialize)]
+struct ApiResponse {
+    #[serde(default)]
+    results: Vec<ElevatorOutage>,
+}
+
+/// All elevators and escalators that are currently out of service.
+pub struct Elevators(pub Vec<ElevatorOutage>);
+
+impl Elevators {
+    pub fn get() -> Result<Elevators> {
+        let request_url = Url::parse(&format!("{}/elevator/index.php", septa_url()))?;
+        let response: ApiResponse = get_json(request_url.as_ref())?;
+        Ok(Elevators(response.results))
+    }
+
+    /// Keeps only the outages at the given station.
+    pub fn at_station(self, station: &Station) -> Elevators {
+        Elevators(self.0.into_iter().filter(|outage| outage.is_at(station)).collect())
+    }
+
+    /// Prints a warning for every outage at any of the given stations.
+    pub fn print_warning(&self, stations: &[&Station]) {
+        for outage in self
+            .0
+            .iter()
+            .filter(|outage| stations.iter().any(|station| outage.is_at(station)))
+        {
+            println!(
+                "{} {}: {}",
+                "Elevator outage at".red().bold(),
+                outage.station.as_deref().unwrap_or("None").red().bold(),
+                outage.elevator.as_deref().unwrap_or("None").red()
+            );
+        }
+    }
+}
+
+impl Parse for Elevators {
+    fn parse(&self) -> Vec<String> {
+        self.0
+            .iter()
+            .map(|outage| {
+                format!(
+                    "{:<27}{:<24}{:<36}{}",
+                    outage.station.as_deref().unwrap_or("None"),
+                    outage.line.as_deref().unwrap_or("None"),
+                    outage.elevator.as_deref().unwrap_or("None"),
+                    outage.message.as_deref().unwrap_or("None"),
+                )
+            })
+            .collect()
+    }
+}
+
+impl PrettyPrint for Elevators {
+    fn print(&self) {
+        if self.0.is_empty() {
+            println!("{}", "No elevator outages".green());
+            return;
+        }
+        println!(
+            "{:<27}{:<24}{:<36}{}",
+            "Station".yellow(),
+            "Line".cyan(),
+            "Elevator".green(),
+            "Message".red(),
+        );
+        for outage in self.parse().iter() {
+            println!("{outage}");
+        }
+    }
+}
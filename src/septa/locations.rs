@@ -0,0 +1,101 @@
+use crate::fetch::get_json;
+use crate::septa_url;
+use crate::traits::{Parse, PrettyPrint};
+use anyhow::{Context, Result, anyhow};
+use colored::Colorize;
+use serde::Deserialize;
+use std::str::FromStr;
+use url::Url;
+
+/// A latitude and longitude pair, written as `lat,lon` (e.g, 39.95,-75.16).
+#[derive(Clone, Debug)]
+pub struct Coordinates {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl FromStr for Coordinates {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (lat, lon) = s
+            .split_once(',')
+            .ok_or(format!("Invalid coordinates: {}, expected LAT,LON", s))?;
+        let lat: f64 = lat.trim().parse().map_err(|_| format!("Invalid latitude: {}", lat))?;
+        let lon: f64 = lon.trim().parse().map_err(|_| format!("Invalid longitude: {}", lon))?;
+        Ok(Coordinates { lat, lon })
+    }
+}
+
+#[derive(Deserialize)]
+pub struct LocationsInner {
+    location_name: String,
+    distance: Option<String>,
+}
+
+impl LocationsInner {
+    pub fn name(&self) -> &str {
+        &self.location_name
+    }
+
+    pub fn distance(&self) -> String {
+        match self
+            .distance
+            .as_deref()
+            .and_then(|distance| distance.parse::<f64>().ok())
+        {
+            Some(distance) => format!("{:.2} mi", distance),
+            None => "None".to_owned(),
+        }
+    }
+}
+
+/// Regional Rail stations closest to a given point, nearest first.
+#[derive(Deserialize)]
+pub struct NearbyStations(pub Vec<LocationsInner>);
+
+/// How far out to look for stations, in miles.
+const SEARCH_RADIUS: u8 = 10;
+
+impl NearbyStations {
+    pub fn get(coordinates: &Coordinates, num: u8) -> Result<NearbyStations> {
+        let request_url = Url::parse(&format!(
+            "{}/locations/get_locations.php?lon={}&lat={}&type=rail_stations&radius={}",
+            septa_url(),
+            coordinates.lon,
+            coordinates.lat,
+            SEARCH_RADIUS
+        ))?;
+        let mut result: NearbyStations = get_json(request_url.as_ref())?;
+        result.0.truncate(num as usize);
+        Ok(result)
+    }
+
+    /// Returns the closest station to a given point.
+    pub fn nearest(coordinates: &Coordinates) -> Result<LocationsInner> {
+        Self::get(coordinates, 1)?
+            .0
+            .into_iter()
+            .next()
+            .ok_or(anyhow!("No stations within {} miles", SEARCH_RADIUS))
+            .context("Unable to find the nearest station")
+    }
+}
+
+impl Parse for NearbyStations {
+    fn parse(&self) -> Vec<String> {
+        self.0
+            .iter()
+            .map(|station| format!("{:<32}{}", station.location_name, station.distance()))
+            .collect()
+    }
+}
+
+impl PrettyPrint for NearbyStations {
+    fn print(&self) {
+        println!("{:<32}{}", "Station".yellow(), "Distance".cyan());
+        for station in self.parse().iter() {
+            println!("{station}");
+        }
+    }
+}
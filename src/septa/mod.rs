@@ -1,7 +1,19 @@
+mod alerts;
 mod arrivals;
+mod bus_stops;
+mod elevators;
+mod locations;
 mod next_to_arrive;
 mod train_schedule;
+mod train_view;
+mod transit_view;
 
+pub use alerts::Alerts;
 pub use arrivals::Arrivals;
+pub use bus_stops::{StopPredictions, Stops};
+pub use elevators::Elevators;
+pub use locations::{Coordinates, NearbyStations};
 pub use next_to_arrive::NextToArrive;
 pub use train_schedule::TrainSchedule;
+pub use train_view::TrainView;
+pub use transit_view::TransitView;
@@ -1,4 +1,5 @@
-use crate::URL;
+use crate::fetch::get_json;
+use crate::septa_url;
 use crate::traits::{Parse, PrettyPrint};
 use anyhow::Result;
 use colored::Colorize;
@@ -29,11 +30,27 @@ impl NextToArrive {
     pub fn get(from: &str, to: &str, num: u8) -> Result<NextToArrive> {
         let request_url = Url::parse(&format!(
             "{}/NextToArrive/index.php?req1={}&req2={}&req3={}",
-            URL, from, to, num,
+            septa_url(),
+            from,
+            to,
+            num,
         ))?;
-        let result: NextToArrive = ureq::get(request_url.as_ref()).call()?.body_mut().read_json()?;
+        let result: NextToArrive = get_json(request_url.as_ref())?;
         Ok(result)
     }
+
+    /// All of the distinct lines used by these trips, including connections.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .0
+            .iter()
+            .flat_map(|train| [train.orig_line.clone(), train.term_line.clone()])
+            .flatten()
+            .collect();
+        lines.sort();
+        lines.dedup();
+        lines
+    }
 }
 
 impl Parse for NextToArrive {
@@ -1,4 +1,5 @@
-use crate::URL;
+use crate::fetch::get_json;
+use crate::septa_url;
 use crate::traits::{Parse, PrettyPrint};
 use anyhow::Result;
 use colored::Colorize;
@@ -19,8 +20,8 @@ pub struct TrainSchedule(pub Vec<TrainScheduleInner>);
 
 impl TrainSchedule {
     pub fn get(num: &str) -> Result<TrainSchedule> {
-        let request_url = Url::parse(&format!("{}/RRSchedules/index.php?req1={}", URL, num))?;
-        let result: TrainSchedule = ureq::get(request_url.as_ref()).call()?.body_mut().read_json()?;
+        let request_url = Url::parse(&format!("{}/RRSchedules/index.php?req1={}", septa_url(), num))?;
+        let result: TrainSchedule = get_json(request_url.as_ref())?;
         Ok(result)
     }
 }
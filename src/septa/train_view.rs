@@ -0,0 +1,125 @@
+use crate::fetch::get_json;
+use crate::septa_url;
+use crate::traits::{Parse, PrettyPrint};
+use anyhow::Result;
+use colored::Colorize;
+use serde::Deserialize;
+use url::Url;
+
+#[derive(Deserialize, Clone)]
+pub struct TrainViewInner {
+    trainno: Option<String>,
+    line: Option<String>,
+    currentstop: Option<String>,
+    nextstop: Option<String>,
+    dest: Option<String>,
+    late: Option<i64>,
+    lat: Option<String>,
+    lon: Option<String>,
+}
+
+impl TrainViewInner {
+    fn lateness(&self) -> String {
+        match self.late {
+            Some(0) => "On time".to_owned(),
+            Some(late) => format!("{} min", late),
+            None => "None".to_owned(),
+        }
+    }
+
+    fn position(&self) -> String {
+        match (self.lat.as_deref(), self.lon.as_deref()) {
+            (Some(lat), Some(lon)) => format!("{}, {}", lat, lon),
+            (_, _) => "None".to_owned(),
+        }
+    }
+}
+
+/// Every Regional Rail train currently running, as reported by SEPTA's TrainView.
+#[derive(Deserialize)]
+pub struct TrainView(pub Vec<TrainViewInner>);
+
+impl TrainView {
+    pub fn get() -> Result<TrainView> {
+        let request_url = Url::parse(&format!("{}/TrainView/index.php", septa_url()))?;
+        let result: TrainView = get_json(request_url.as_ref())?;
+        Ok(result)
+    }
+
+    /// Keeps only the trains whose line name contains `line`, ignoring case.
+    pub fn filter_line(&mut self, line: &str) {
+        let line = line.to_lowercase();
+        self.0.retain(|train| {
+            train
+                .line
+                .as_deref()
+                .is_some_and(|name| name.to_lowercase().contains(&line))
+        });
+    }
+
+    /// Sorts the trains so the latest ones come first.
+    pub fn sort_by_delay(&mut self) {
+        self.0.sort_by_key(|train| std::cmp::Reverse(train.late.unwrap_or(0)));
+    }
+
+    /// Returns the live position of a given train, if it's currently running.
+    pub fn find(self, number: &str) -> Option<TrainViewInner> {
+        self.0
+            .into_iter()
+            .find(|train| train.trainno.as_deref() == Some(number))
+    }
+}
+
+impl Parse for TrainView {
+    fn parse(&self) -> Vec<String> {
+        self.0
+            .iter()
+            .map(|train| {
+                format!(
+                    "{:<9}{:<22}{:<27}{:<24}{:<9}{}",
+                    train.trainno.as_deref().unwrap_or("None"),
+                    train.line.as_deref().unwrap_or("None"),
+                    train.nextstop.as_deref().unwrap_or("None"),
+                    train.dest.as_deref().unwrap_or("None"),
+                    train.lateness(),
+                    train.position(),
+                )
+            })
+            .collect()
+    }
+}
+
+impl PrettyPrint for TrainView {
+    fn print(&self) {
+        println!(
+            "{:<9}{:<22}{:<27}{:<24}{:<9}{}",
+            "Train #".cyan(),
+            "Line".yellow(),
+            "Next Stop".green(),
+            "Destination".blue(),
+            "Late".red(),
+            "Position".magenta(),
+        );
+        for train in self.parse().iter() {
+            println!("{train}");
+        }
+    }
+}
+
+impl PrettyPrint for TrainViewInner {
+    fn print(&self) {
+        println!("{}", "Live Position".green().bold());
+        println!(
+            "{:<14}{}",
+            "Current Stop".yellow(),
+            self.currentstop.as_deref().unwrap_or("None")
+        );
+        println!(
+            "{:<14}{}",
+            "Next Stop".yellow(),
+            self.nextstop.as_deref().unwrap_or("None")
+        );
+        println!("{:<14}{}", "Late".yellow(), self.lateness());
+        println!("{:<14}{}", "Position".yellow(), self.position());
+    }
+}
@@ -0,0 +1,85 @@
+use crate::fetch::get_json;
+use crate::septa_url;
+use crate::traits::{Parse, PrettyPrint};
+use anyhow::Result;
+use colored::Colorize;
+use serde::Deserialize;
+use url::Url;
+
+#[derive(Deserialize)]
+pub struct Vehicle {
+    label: Option<String>,
+    #[serde(rename = "Direction")]
+    direction: Option<String>,
+    destination: Option<String>,
+    next_stop_name: Option<String>,
+    late: Option<i64>,
+}
+
+impl Vehicle {
+    fn lateness(&self) -> String {
+        match self.late {
+            Some(0) => "On time".to_owned(),
+            // SEPTA uses 998 and 999 when there is no estimate for a vehicle
+            Some(998..) | None => "None".to_owned(),
+            Some(late) if late < 0 => format!("{} min early", -late),
+            Some(late) => format!("{} min", late),
+        }
+    }
+}
+
+/// Intermediate structure matching the SEPTA API response format.
+#[derive(Deserialize)]
+struct ApiResponse {
+    #[serde(default)]
+    bus: Vec<Vehicle>,
+}
+
+/// Every bus or trolley currently running on a given route.
+pub struct TransitView(pub Vec<Vehicle>);
+
+impl TransitView {
+    pub fn get(route: &str) -> Result<TransitView> {
+        let request_url = Url::parse(&format!("{}/TransitView/index.php?route={}", septa_url(), route))?;
+        let response: ApiResponse = get_json(request_url.as_ref())?;
+        Ok(TransitView(response.bus))
+    }
+}
+
+impl Parse for TransitView {
+    fn parse(&self) -> Vec<String> {
+        self.0
+            .iter()
+            .map(|vehicle| {
+                format!(
+                    "{:<10}{:<13}{:<34}{:<28}{}",
+                    vehicle.label.as_deref().unwrap_or("None"),
+                    vehicle.direction.as_deref().unwrap_or("None"),
+                    vehicle.next_stop_name.as_deref().unwrap_or("None"),
+                    vehicle.destination.as_deref().unwrap_or("None"),
+                    vehicle.lateness(),
+                )
+            })
+            .collect()
+    }
+}
+
+impl PrettyPrint for TransitView {
+    fn print(&self) {
+        if self.0.is_empty() {
+            println!("{}", "No vehicles currently running on this route".yellow());
+            return;
+        }
+        println!(
+            "{:<10}{:<13}{:<34}{:<28}{}",
+            "Vehicle".cyan(),
+            "Direction".blue(),
+            "Next Stop".green(),
+            "Destination".yellow(),
+            "Late".red(),
+        );
+        for vehicle in self.parse().iter() {
+            println!("{vehicle}");
+        }
+    }
+}
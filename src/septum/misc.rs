@@ -1,20 +1,28 @@
 use super::ScheduleDirection;
+use crate::cache::{septum_dir, septum_ttl};
+use crate::envelope;
+use crate::fetch::{get_json, is_capturing};
+use crate::matching::best_match;
 use crate::traits::{Parse, PrettyPrint};
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, anyhow};
 use colored::Colorize;
-use serde::Deserialize;
-use skimple::SkimpleMatcher;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::env;
 use url::Url;
 
-#[allow(dead_code)]
-#[derive(Deserialize, Debug)]
+/// Version of the cached lines and per-line stations, bumped whenever they change shape.
+const SEPTUM_FORMAT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct LinesInner {
-    line_code: String,
-    line_name: String,
+    pub line_code: String,
+    pub line_name: String,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Lines(pub Vec<LinesInner>);
 
 impl Parse for Lines {
@@ -35,19 +43,21 @@ impl PrettyPrint for Lines {
     }
 }
 
-#[allow(dead_code)]
-#[derive(Deserialize, Debug)]
+/// A stop on a line, as named by Septum.
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct LinesStationsInner {
-    stop_id: String,
-    stop_name: String,
+    pub stop_id: String,
+    pub stop_name: String,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct LineStations(pub Vec<LinesStationsInner>);
 
+/// Septum's lines and per-line stations, cached on disk and fetched at most once per run.
 pub struct SeptumMisc {
     url: String,
-    matcher: SkimpleMatcher,
+    lines: RefCell<Option<Lines>>,
+    stations: RefCell<HashMap<String, LineStations>>,
 }
 
 impl SeptumMisc {
@@ -56,44 +66,173 @@ impl SeptumMisc {
 
         Ok(SeptumMisc {
             url: base_url,
-            matcher: SkimpleMatcher::default(),
+            lines: RefCell::new(None),
+            stations: RefCell::new(HashMap::new()),
         })
     }
 
     pub fn get_lines(&self) -> Result<Lines> {
-        let request_url = format!("{}/schedule/lines", &self.url);
-        let result: Lines = ureq::get(request_url).call()?.body_mut().read_json()?;
+        if let Some(lines) = self.lines.borrow().as_ref() {
+            return Ok(lines.clone());
+        }
+        let request_url = format!("{}/schedule/lines", self.url);
+        let result: Lines = Self::get_cached("lines", &request_url)?;
+        *self.lines.borrow_mut() = Some(result.clone());
         Ok(result)
     }
 
-    pub fn get_stations_for_line(&self, line: &str, direction: &ScheduleDirection) -> Result<Vec<String>> {
-        let request_url = Url::parse(&format!(
-            "{}/schedule/stations?line={}&direction={}",
-            self.url,
-            line,
-            direction.to_string()
-        ))?;
-        let result: LineStations = ureq::get(request_url.as_ref()).call()?.body_mut().read_json()?;
-        let stations: Vec<String> = result.0.into_iter().map(|item| item.stop_name).collect();
-        Ok(stations)
+    /// Picks the line that best matches `search`, by code or by name (e.g, TRE, trenton or "Paoli Thorndale").
+    pub fn match_line(&self, search: &str) -> Result<LinesInner> {
+        let lines = self.get_lines()?.0;
+        let mut terms: Vec<(String, usize)> = Vec::new();
+        for (index, line) in lines.iter().enumerate() {
+            terms.push((line.line_code.clone(), index));
+            terms.push((line.line_name.clone(), index));
+            // So "Paoli Thorndale" matches "Paoli/Thorndale"
+            terms.push((line.line_name.replace(|c: char| !c.is_alphanumeric(), " "), index));
+        }
+        let labels: Vec<String> = lines
+            .iter()
+            .map(|line| format!("{} ({})", line.line_code, line.line_name))
+            .collect();
+
+        let index = best_match(&terms, &labels, search, |_| 0)?;
+        Ok(lines[index].clone())
     }
-    pub fn fuzzy_match_station_for_line(
-        &mut self,
-        line: &str,
-        search: &str,
-        direction: &ScheduleDirection,
-    ) -> Result<String> {
-        let request_url = Url::parse(&format!(
+
+    pub fn get_stations_for_line(&self, line: &str, direction: &ScheduleDirection) -> Result<Vec<LinesStationsInner>> {
+        let request_url = self.stations_url(line, direction)?;
+        if let Some(stations) = self.stations.borrow().get(request_url.as_str()) {
+            return Ok(stations.0.clone());
+        }
+
+        let name: String = format!("stations_{}_{}", line, direction)
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+            .collect();
+        let result: LineStations = Self::get_cached(&name.to_lowercase(), request_url.as_str())?;
+        self.stations
+            .borrow_mut()
+            .insert(request_url.to_string(), result.clone());
+        Ok(result.0)
+    }
+
+    fn stations_url(&self, line: &str, direction: &ScheduleDirection) -> Result<Url> {
+        Ok(Url::parse(&format!(
             "{}/schedule/stations?line={}&direction={}",
-            self.url,
-            line,
-            direction.to_string()
-        ))?;
-        let result: LineStations = ureq::get(request_url.as_ref()).call()?.body_mut().read_json()?;
-        let stations: Vec<String> = result.0.into_iter().map(|item| item.stop_name).collect();
+            self.url, line, direction
+        ))?)
+    }
+
+    /// Works out which direction goes from one stop to the other, from the order of the line's stations.
+    ///
+    /// Returns `None` if neither direction has both stops in that order (e.g, the same stop twice).
+    pub fn infer_direction(&self, line: &str, orig: &str, dest: &str) -> Result<Option<ScheduleDirection>> {
+        for direction in [ScheduleDirection::Inbound, ScheduleDirection::Outbound] {
+            let stops = self.get_stations_for_line(line, &direction)?;
+            let position = |stop_id: &str| stops.iter().position(|stop| stop.stop_id == stop_id);
+            if let (Some(orig), Some(dest)) = (position(orig), position(dest))
+                && orig < dest
+            {
+                return Ok(Some(direction));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Serves `url` from the Septum cache while it's fresh, fetching and caching it otherwise.
+    ///
+    /// An outdated copy is still used if fetching fails. The cache is left alone entirely while
+    /// recording or replaying a capture.
+    fn get_cached<T: Serialize + DeserializeOwned + Clone>(name: &str, url: &str) -> Result<T> {
+        if is_capturing() {
+            return get_json(url);
+        }
 
-        let station = self.matcher.fuzzy_best(&stations, search)?.to_owned();
+        let path = septum_dir()?.join(name);
+        let cached = envelope::read::<T, _>(&path, SEPTUM_FORMAT_VERSION, |version, _| {
+            Err(anyhow!("Unknown Septum cache version {:?}", version))
+        });
+        if let Ok(cached) = &cached
+            && cached.header.source == url
+            && septum_ttl().is_ok_and(|ttl| cached.age() <= ttl)
+        {
+            return Ok(cached.payload.clone());
+        }
+
+        match get_json::<T>(url) {
+            Ok(result) => {
+                let _ = envelope::write(&path, SEPTUM_FORMAT_VERSION, url, &result);
+                Ok(result)
+            }
+            Err(err) => cached.map(|cached| cached.payload).map_err(|_| err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A Septum client whose stations for `TRE` are already memoized, so nothing is fetched.
+    fn septum(inbound: &[&str], outbound: &[&str]) -> SeptumMisc {
+        let septum = SeptumMisc {
+            url: "http://septum.invalid/api".to_owned(),
+            lines: RefCell::new(None),
+            stations: RefCell::new(HashMap::new()),
+        };
+        for (direction, stops) in [
+            (ScheduleDirection::Inbound, inbound),
+            (ScheduleDirection::Outbound, outbound),
+        ] {
+            let url = septum.stations_url("TRE", &direction).unwrap();
+            let stops = stops
+                .iter()
+                .map(|stop| LinesStationsInner {
+                    stop_id: stop.to_string(),
+                    stop_name: stop.to_string(),
+                })
+                .collect();
+            septum
+                .stations
+                .borrow_mut()
+                .insert(url.to_string(), LineStations(stops));
+        }
+        septum
+    }
+
+    fn direction(septum: &SeptumMisc, orig: &str, dest: &str) -> Option<ScheduleDirection> {
+        septum.infer_direction("TRE", orig, dest).unwrap()
+    }
+
+    #[test]
+    fn infers_inbound_from_the_inbound_order() {
+        let septum = septum(&["1", "2", "3"], &["3", "2", "1"]);
+        assert!(matches!(direction(&septum, "1", "3"), Some(ScheduleDirection::Inbound)));
+    }
+
+    #[test]
+    fn infers_outbound_from_the_outbound_order() {
+        let septum = septum(&["1", "2", "3"], &["3", "2", "1"]);
+        assert!(matches!(
+            direction(&septum, "3", "2"),
+            Some(ScheduleDirection::Outbound)
+        ));
+    }
+
+    #[test]
+    fn has_no_direction_for_the_same_stop_twice() {
+        let septum = septum(&["1", "2", "3"], &["3", "2", "1"]);
+        assert!(direction(&septum, "2", "2").is_none());
+    }
 
-        Ok(station)
+    #[test]
+    fn skips_a_direction_missing_one_of_the_stops() {
+        let septum = septum(&["1", "2", "3"], &["3", "2"]);
+        assert!(matches!(
+            direction(&septum, "3", "2"),
+            Some(ScheduleDirection::Outbound)
+        ));
+        assert!(direction(&septum, "2", "1").is_none());
     }
 }
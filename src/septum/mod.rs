@@ -1,5 +1,5 @@
 mod misc;
 mod schedule;
 
-pub use misc::SeptumMisc;
-pub use schedule::{ScheduleDirection, ScheduleMode, ScheduleOuter};
+pub use misc::{LinesInner, LinesStationsInner, SeptumMisc};
+pub use schedule::{ScheduleDirection, ScheduleMode, ScheduleOuter, ScheduleWindow, Trip};
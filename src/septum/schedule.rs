@@ -1,17 +1,19 @@
+use crate::fetch::get_json;
+use crate::service::ServiceTime;
 use crate::traits::{ParseWithMode, PrettyPrintWithMode};
-use crate::utils::parse_time;
 use anyhow::{Context, Result as AnyResult};
 use colored::Colorize;
 use serde::Deserialize;
 use std::env;
+use std::ops::Range;
 use std::str::FromStr;
 use url::Url;
 
 #[allow(dead_code)]
 #[derive(Deserialize, Debug)]
 pub struct ScheduleInner {
-    departure_time: String,
-    arrival_time: String,
+    departure_time: ServiceTime,
+    arrival_time: ServiceTime,
     train_id: String,
 }
 
@@ -22,6 +24,23 @@ pub struct ScheduleOuter {
     weekend: Vec<ScheduleInner>,
 }
 
+/// Which of a day's trains to show, by departure time.
+#[derive(Default)]
+pub struct ScheduleWindow {
+    pub after: Option<ServiceTime>,
+    pub before: Option<ServiceTime>,
+    pub limit: Option<usize>,
+}
+
+/// A single trip to pick a train for.
+#[derive(Clone, Copy)]
+pub enum Trip {
+    /// Be at the destination by this time
+    ArriveBy(ServiceTime),
+    /// Leave the origin no earlier than this time
+    DepartAfter(ServiceTime),
+}
+
 #[derive(Clone, Debug)]
 pub enum ScheduleMode {
     Weekday,
@@ -72,58 +91,238 @@ impl ScheduleOuter {
         let base_url = env::var("SeptumURL").context("SeptumURL not set. Set it with: export SeptumURL=https://...")?;
         let request_url = Url::parse(&format!(
             "{}/schedule?line={}&direction={}&orig={}&dest={}",
-            base_url,
-            line,
-            direction.to_string(),
-            orig,
-            dest
+            base_url, line, direction, orig, dest
         ))?;
-        let result: ScheduleOuter = ureq::get(request_url.as_ref()).call()?.body_mut().read_json()?;
+        let mut result: ScheduleOuter = get_json(request_url.as_ref())?;
+        for trains in [&mut result.weekday, &mut result.weekend] {
+            trains.sort_by_key(|train| train.departure_time);
+        }
         Ok(result)
     }
+
+    /// Keeps only the trains departing within `window`.
+    pub fn filter(&mut self, window: &ScheduleWindow) {
+        // A window ending before it starts runs past midnight (e.g, 23:00 to 01:00)
+        let before = match (window.after, window.before) {
+            (Some(after), Some(before)) if before < after => Some(before.next_day()),
+            (_, before) => before,
+        };
+
+        for trains in [&mut self.weekday, &mut self.weekend] {
+            trains.retain(|train| {
+                window.after.is_none_or(|after| train.departure_time >= after)
+                    && before.is_none_or(|before| train.departure_time <= before)
+            });
+            if let Some(limit) = window.limit {
+                trains.truncate(limit);
+            }
+        }
+    }
+
+    /// Adds the trains of the previous service day running past midnight to this day's, on
+    /// this day's clock (e.g, a 24:55 train yesterday becomes a 00:55 train today).
+    pub fn carry_over(&mut self, previous: &ScheduleMode, mode: &ScheduleMode) {
+        let late: Vec<ScheduleInner> = self
+            .trains(previous)
+            .iter()
+            .filter(|train| train.departure_time.is_next_day())
+            .map(|train| ScheduleInner {
+                departure_time: train.departure_time.previous_day(),
+                arrival_time: train.arrival_time.previous_day(),
+                train_id: train.train_id.clone(),
+            })
+            .collect();
+
+        let trains = match mode {
+            ScheduleMode::Weekday => &mut self.weekday,
+            ScheduleMode::Weekend => &mut self.weekend,
+        };
+        trains.extend(late);
+        trains.sort_by_key(|train| train.departure_time);
+    }
+
+    /// Picks the train that best fits `trip`, returning its position among the day's trains.
+    ///
+    /// That's the latest train arriving in time, or the earliest one departing late enough.
+    pub fn plan(&self, mode: &ScheduleMode, trip: Trip) -> Option<usize> {
+        let trains = self.trains(mode);
+        match trip {
+            Trip::ArriveBy(by) => trains
+                .iter()
+                .enumerate()
+                .filter(|(_, train)| train.arrival_time <= by)
+                .max_by_key(|(_, train)| (train.arrival_time, train.departure_time))
+                .map(|(index, _)| index),
+            Trip::DepartAfter(after) => trains.iter().position(|train| train.departure_time >= after),
+        }
+    }
+
+    /// Prints the train picked for a trip, along with the trains right before and after it.
+    pub fn print_plan(&self, mode: &ScheduleMode, chosen: usize) {
+        print_header();
+        let trains = self.trains(mode);
+        for index in alternatives(chosen, trains.len()) {
+            match index == chosen {
+                true => println!("{}  {}", trains[index].row().bold(), "<- Take this train".green()),
+                false => println!("{}", trains[index].row().dimmed()),
+            }
+        }
+    }
+
+    fn trains(&self, mode: &ScheduleMode) -> &[ScheduleInner] {
+        match mode {
+            ScheduleMode::Weekday => &self.weekday,
+            ScheduleMode::Weekend => &self.weekend,
+        }
+    }
+}
+
+impl ScheduleInner {
+    fn row(&self) -> String {
+        format!(
+            "{:<8}{:<14}{}",
+            self.train_id.as_str(),
+            self.departure_time.to_string(),
+            self.arrival_time,
+        )
+    }
 }
 
 impl ParseWithMode for ScheduleOuter {
     fn parse(&self, mode: &ScheduleMode) -> Vec<String> {
-        match mode {
-            ScheduleMode::Weekday => self
-                .weekday
-                .iter()
-                .map(|train| {
-                    format!(
-                        "{:<8}{:<14}{}",
-                        train.train_id.as_str(),
-                        parse_time(train.departure_time.as_str()),
-                        parse_time(train.arrival_time.as_str()),
-                    )
-                })
-                .collect(),
-            ScheduleMode::Weekend => self
-                .weekend
-                .iter()
-                .map(|train| {
-                    format!(
-                        "{:<8}{:<14}{}",
-                        train.train_id.as_str(),
-                        parse_time(train.departure_time.as_str()),
-                        parse_time(train.arrival_time.as_str()),
-                    )
-                })
-                .collect(),
-        }
+        self.trains(mode).iter().map(ScheduleInner::row).collect()
     }
 }
 
 impl PrettyPrintWithMode for ScheduleOuter {
     fn print(&self, mode: &ScheduleMode) {
-        println!(
-            "{:<8}{:<14}{}",
-            "Train".yellow(),
-            "Depart Time".cyan(),
-            "Arrive Time".green(),
-        );
+        print_header();
         for train in self.parse(mode).iter() {
             println!("{train}");
         }
     }
 }
+
+/// The positions of the chosen train and the trains right before and after it, out of `count`.
+fn alternatives(chosen: usize, count: usize) -> Range<usize> {
+    chosen.saturating_sub(1)..(chosen + 2).min(count)
+}
+
+fn print_header() {
+    println!(
+        "{:<8}{:<14}{}",
+        "Train".yellow(),
+        "Depart Time".cyan(),
+        "Arrive Time".green(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule() -> ScheduleOuter {
+        serde_json::from_str(
+            r#"{
+                "weekday": [
+                    {"departure_time": "24:55:00", "arrival_time": "25:50:00", "train_id": "3"},
+                    {"departure_time": "05:10:00", "arrival_time": "06:05:00", "train_id": "1"},
+                    {"departure_time": "23:50:00", "arrival_time": "24:45:00", "train_id": "2"}
+                ],
+                "weekend": [
+                    {"departure_time": "06:50:00", "arrival_time": "07:45:00", "train_id": "4"}
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn train_ids(schedule: &ScheduleOuter, mode: &ScheduleMode) -> Vec<String> {
+        schedule
+            .parse(mode)
+            .iter()
+            .map(|row| row.split_whitespace().next().unwrap().to_owned())
+            .collect()
+    }
+
+    fn time(s: &str) -> Option<ServiceTime> {
+        Some(s.parse().unwrap())
+    }
+
+    #[test]
+    fn filters_across_midnight() {
+        let mut schedule = schedule();
+        schedule.weekday.sort_by_key(|train| train.departure_time);
+        schedule.filter(&ScheduleWindow {
+            after: time("23:00"),
+            before: time("01:00"),
+            limit: None,
+        });
+        assert_eq!(train_ids(&schedule, &ScheduleMode::Weekday), ["2", "3"]);
+    }
+
+    #[test]
+    fn limits_to_the_earliest_trains() {
+        let mut schedule = schedule();
+        schedule.weekday.sort_by_key(|train| train.departure_time);
+        schedule.filter(&ScheduleWindow {
+            after: time("05:00"),
+            before: None,
+            limit: Some(2),
+        });
+        assert_eq!(train_ids(&schedule, &ScheduleMode::Weekday), ["1", "2"]);
+    }
+
+    #[test]
+    fn carries_over_trains_after_midnight() {
+        let mut schedule = schedule();
+        schedule.carry_over(&ScheduleMode::Weekday, &ScheduleMode::Weekend);
+        assert_eq!(train_ids(&schedule, &ScheduleMode::Weekend), ["3", "4"]);
+        assert_eq!(schedule.weekend[0].departure_time, time("00:55").unwrap());
+    }
+
+    fn plan(trip: Trip) -> Option<String> {
+        let mut schedule: ScheduleOuter = serde_json::from_str(
+            r#"{
+                "weekday": [
+                    {"departure_time": "07:02:00", "arrival_time": "07:57:00", "train_id": "1"},
+                    {"departure_time": "07:45:00", "arrival_time": "08:40:00", "train_id": "2"},
+                    {"departure_time": "07:50:00", "arrival_time": "08:40:00", "train_id": "3"},
+                    {"departure_time": "08:30:00", "arrival_time": "09:25:00", "train_id": "4"}
+                ],
+                "weekend": []
+            }"#,
+        )
+        .unwrap();
+        schedule.weekday.sort_by_key(|train| train.departure_time);
+        let chosen = schedule.plan(&ScheduleMode::Weekday, trip)?;
+        Some(schedule.weekday[chosen].train_id.clone())
+    }
+
+    #[test]
+    fn arrives_by_on_the_latest_departure_among_ties() {
+        assert_eq!(plan(Trip::ArriveBy(time("09:00").unwrap())).as_deref(), Some("3"));
+        assert_eq!(plan(Trip::ArriveBy(time("08:40").unwrap())).as_deref(), Some("3"));
+        assert_eq!(plan(Trip::ArriveBy(time("08:39").unwrap())).as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn finds_no_train_in_time() {
+        assert_eq!(plan(Trip::ArriveBy(time("07:56").unwrap())), None);
+        assert_eq!(plan(Trip::DepartAfter(time("08:31").unwrap())), None);
+    }
+
+    #[test]
+    fn departs_after_at_an_exact_departure_time() {
+        assert_eq!(plan(Trip::DepartAfter(time("07:45").unwrap())).as_deref(), Some("2"));
+        assert_eq!(plan(Trip::DepartAfter(time("07:46").unwrap())).as_deref(), Some("3"));
+    }
+
+    #[test]
+    fn shows_one_alternative_on_each_side() {
+        assert_eq!(alternatives(0, 4), 0..2);
+        assert_eq!(alternatives(2, 4), 1..4);
+        assert_eq!(alternatives(3, 4), 2..4);
+        assert_eq!(alternatives(0, 1), 0..1);
+    }
+}
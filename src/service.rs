@@ -0,0 +1,233 @@
+use crate::cache::read_config;
+use crate::septum::ScheduleMode;
+use crate::utils::format_time;
+use anyhow::{Context, Result};
+use jiff::Zoned;
+use jiff::civil::{Date, Weekday, date};
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// Where SEPTA runs, and so what "today" means for schedules.
+const SERVICE_TIME_ZONE: &str = "America/New_York";
+
+/// Today's date in Philadelphia, regardless of the local time zone.
+pub fn today() -> Result<Date> {
+    Ok(now()?.date())
+}
+
+fn now() -> Result<Zoned> {
+    Zoned::now()
+        .in_tz(SERVICE_TIME_ZONE)
+        .context("Unable to get the time in Philadelphia")
+}
+
+/// A time on a service day, in minutes since midnight.
+///
+/// Trains running past midnight belong to the previous day's service and keep counting up
+/// (e.g, 25:10 for 1:10 AM), so they sort after 23:xx instead of before 00:xx.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ServiceTime(u32);
+
+impl ServiceTime {
+    const DAY: u32 = 24 * 60;
+
+    /// The time right now in Philadelphia.
+    pub fn now() -> Result<Self> {
+        let now = now()?;
+        Ok(ServiceTime(now.hour() as u32 * 60 + now.minute() as u32))
+    }
+
+    /// This time a number of minutes earlier, or `None` if that goes back past midnight.
+    pub fn earlier(self, minutes: u32) -> Option<Self> {
+        self.0.checked_sub(minutes).map(ServiceTime)
+    }
+
+    /// This time a number of minutes later.
+    pub fn later(self, minutes: u32) -> Self {
+        ServiceTime(self.0 + minutes)
+    }
+
+    /// Whether this time is past midnight, on the next calendar day.
+    pub fn is_next_day(self) -> bool {
+        self.0 >= Self::DAY
+    }
+
+    /// The same time of day, a day earlier (e.g, 24:30 becomes 00:30).
+    pub fn previous_day(self) -> Self {
+        ServiceTime(self.0.saturating_sub(Self::DAY))
+    }
+
+    /// The same time of day, a day later (e.g, 00:30 becomes 24:30).
+    pub fn next_day(self) -> Self {
+        ServiceTime(self.0 + Self::DAY)
+    }
+}
+
+impl FromStr for ServiceTime {
+    type Err = String;
+
+    /// Parses `HH:MM` or `HH:MM:SS`, with hours past 23 for trains running after midnight.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid time: {} (expected HH:MM)", s);
+        let mut parts = s.trim().split(':');
+        let hour: u32 = parts.next().and_then(|h| h.parse().ok()).ok_or_else(invalid)?;
+        let minute: u32 = parts.next().and_then(|m| m.parse().ok()).ok_or_else(invalid)?;
+        let second_ok = parts.next().is_none_or(|s| s.parse::<u32>().is_ok_and(|s| s < 60));
+        if hour >= 48 || minute >= 60 || !second_ok || parts.next().is_some() {
+            return Err(invalid());
+        }
+        Ok(ServiceTime(hour * 60 + minute))
+    }
+}
+
+impl TryFrom<String> for ServiceTime {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for ServiceTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (hour, minute) = (self.0 / 60, self.0 % 60);
+        write!(f, "{}", format_time(hour as u8, minute as u8))
+    }
+}
+
+/// Which schedule runs on the given date, along with the holiday responsible for it, if any.
+///
+/// Saturdays, Sundays and holidays run weekend service, every other day runs weekday service.
+pub fn service_for(day: Date) -> (ScheduleMode, Option<String>) {
+    service_among(day, holidays(day.year()))
+}
+
+/// Same as `service_for`, with the given holidays, later ones taking precedence.
+fn service_among(day: Date, holidays: Vec<(Date, ScheduleMode, String)>) -> (ScheduleMode, Option<String>) {
+    if let Some((mode, name)) = holidays
+        .into_iter()
+        .rev()
+        .find_map(|(holiday, mode, name)| (holiday == day).then_some((mode, name)))
+    {
+        return (mode, Some(name));
+    }
+
+    match day.weekday() {
+        Weekday::Saturday | Weekday::Sunday => (ScheduleMode::Weekend, None),
+        _ => (ScheduleMode::Weekday, None),
+    }
+}
+
+/// SEPTA's holidays for the given year, followed by the user's own.
+///
+/// User holidays live in `holidays` in the config directory, one `YYYY-MM-DD = weekday|weekend`
+/// per line, and take precedence over the built-in ones (e.g, to run weekday service on a
+/// holiday). Lines starting with `#` are ignored.
+fn holidays(year: i16) -> Vec<(Date, ScheduleMode, String)> {
+    let mut holidays: Vec<(Date, ScheduleMode, String)> = builtin_holidays(year)
+        .into_iter()
+        .map(|(day, name)| (day, ScheduleMode::Weekend, name.to_owned()))
+        .collect();
+
+    holidays.extend(read_config("holidays", |day, mode| {
+        Some((day.parse().ok()?, mode.parse().ok()?, "holidays file".to_owned()))
+    }));
+    holidays
+}
+
+fn builtin_holidays(year: i16) -> Vec<(Date, &'static str)> {
+    let nth = |month: i8, nth: i8, weekday: Weekday| date(year, month, 1).nth_weekday_of_month(nth, weekday).ok();
+    [
+        Some((date(year, 1, 1), "New Year's Day")),
+        nth(5, -1, Weekday::Monday).map(|day| (day, "Memorial Day")),
+        Some((date(year, 7, 4), "Independence Day")),
+        nth(9, 1, Weekday::Monday).map(|day| (day, "Labor Day")),
+        nth(11, 4, Weekday::Thursday).map(|day| (day, "Thanksgiving")),
+        Some((date(year, 12, 25), "Christmas Day")),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(s: &str) -> ServiceTime {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_service_times() {
+        assert_eq!(time("05:10"), ServiceTime(5 * 60 + 10));
+        assert_eq!(time("05:10:00"), time("5:10"));
+        assert_eq!(time("25:30"), ServiceTime(25 * 60 + 30));
+        for invalid in ["", "5pm", "12", "12:60", "48:00", "12:00:60", "12:00:00:00"] {
+            assert!(invalid.parse::<ServiceTime>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn orders_trains_after_midnight_last() {
+        assert!(time("23:50") < time("24:55"));
+        assert!(time("24:55") < time("25:10"));
+        assert_eq!(time("00:30").next_day(), time("24:30"));
+        assert_eq!(time("24:30").previous_day(), time("00:30"));
+        assert_eq!(time("24:55").to_string(), "12:55 AM");
+    }
+
+    #[test]
+    fn never_moves_before_midnight() {
+        assert_eq!(time("09:00").earlier(10), Some(time("08:50")));
+        assert_eq!(time("00:05").earlier(5), Some(time("00:00")));
+        assert_eq!(time("00:05").earlier(10), None);
+        assert_eq!(time("23:55").later(10), time("24:05"));
+    }
+
+    fn service(day: Date, user: &[(Date, ScheduleMode)]) -> (ScheduleMode, Option<String>) {
+        let mut holidays: Vec<(Date, ScheduleMode, String)> = builtin_holidays(day.year())
+            .into_iter()
+            .map(|(day, name)| (day, ScheduleMode::Weekend, name.to_owned()))
+            .collect();
+        holidays.extend(
+            user.iter()
+                .map(|(day, mode)| (*day, mode.clone(), "holidays file".to_owned())),
+        );
+        service_among(day, holidays)
+    }
+
+    #[test]
+    fn runs_weekend_service_on_weekends_and_holidays() {
+        assert!(matches!(
+            service(date(2026, 10, 19), &[]),
+            (ScheduleMode::Weekday, None)
+        ));
+        assert!(matches!(
+            service(date(2026, 10, 18), &[]),
+            (ScheduleMode::Weekend, None)
+        ));
+        let (mode, holiday) = service(date(2026, 11, 26), &[]);
+        assert!(matches!(mode, ScheduleMode::Weekend));
+        assert_eq!(holiday.as_deref(), Some("Thanksgiving"));
+    }
+
+    #[test]
+    fn lets_user_holidays_override_builtin_ones() {
+        let thanksgiving = date(2026, 11, 26);
+        let (mode, holiday) = service(thanksgiving, &[(thanksgiving, ScheduleMode::Weekday)]);
+        assert!(matches!(mode, ScheduleMode::Weekday));
+        assert_eq!(holiday.as_deref(), Some("holidays file"));
+    }
+
+    #[test]
+    fn finds_floating_holidays() {
+        let holidays = builtin_holidays(2026);
+        let on = |name: &str| holidays.iter().find(|(_, own)| *own == name).map(|(day, _)| *day);
+        assert_eq!(on("Memorial Day"), Some(date(2026, 5, 25)));
+        assert_eq!(on("Labor Day"), Some(date(2026, 9, 7)));
+        assert_eq!(on("Thanksgiving"), Some(date(2026, 11, 26)));
+    }
+}
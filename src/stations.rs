@@ -1,29 +1,120 @@
+use crate::aliases::aliases;
+use crate::cache::{stations_path, stations_ttl};
+use crate::envelope::{self, Envelope};
+use crate::fetch::{get_json, get_json_online, is_capturing};
+use crate::history::History;
+use crate::matching::{best_match, choose, rank};
+use crate::registry::StationRegistry;
+use crate::septum::{LinesInner, LinesStationsInner, ScheduleDirection, SeptumMisc};
+use crate::traits::{Parse, PrettyPrint};
 use anyhow::{Context, Result, anyhow};
-use bincode::{deserialize_from, serialize_into};
-use directories::ProjectDirs;
-use serde::Deserialize;
-use skimple::SkimpleMatcher;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
 use std::env;
-use std::fs;
-use std::fs::{OpenOptions, create_dir_all};
-use std::io::{BufReader, BufWriter};
-use std::time::SystemTime;
+use std::fmt;
+use std::iter;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// A single Regional Rail station, as known to both SEPTA and Septum.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Station {
+    /// The name SEPTA's realtime endpoints expect (e.g, "30th Street Station")
+    pub parameter: String,
+    /// The full display name of the station (e.g, "30th Street")
+    pub name: String,
+    /// Other names the station goes by
+    pub aliases: Vec<String>,
+    /// Septum stop ids for this station
+    pub stop_ids: Vec<String>,
+    /// Codes of the lines serving this station (e.g, TRE), filled in by `link_lines`
+    #[serde(skip)]
+    pub lines: Vec<String>,
+    /// Fare zone (e.g, C or 3), filled in from `KNOWN_STATIONS`
+    #[serde(skip)]
+    pub zone: Option<String>,
+}
+
+impl Station {
+    fn new(parameter: &str, name: &str) -> Self {
+        Station {
+            parameter: parameter.to_owned(),
+            name: name.to_owned(),
+            aliases: Vec::new(),
+            stop_ids: Vec::new(),
+            lines: Vec::new(),
+            zone: None,
+        }
+    }
+
+    /// Parses a label as cached by older versions, the inverse of `Display`.
+    fn from_label(label: &str) -> Self {
+        match label.split_once(" (") {
+            Some((parameter, name)) => Station::new(parameter, name.strip_suffix(')').unwrap_or(name)),
+            None => Station::new(label, label),
+        }
+    }
+
+    /// Whether the station goes by the given name (ignoring case), aliases included.
+    pub fn is_named(&self, name: &str) -> bool {
+        iter::once(&self.parameter)
+            .chain(iter::once(&self.name))
+            .chain(self.aliases.iter())
+            .any(|own| own.eq_ignore_ascii_case(name))
+    }
+
+    /// Every name this station can be matched by.
+    fn search_terms(&self) -> Vec<String> {
+        let mut terms = vec![self.parameter.clone(), self.name.clone(), self.to_string()];
+        terms.extend(self.aliases.iter().cloned());
+        terms
+    }
+}
+
+impl fmt::Display for Station {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.parameter == self.name {
+            write!(f, "{}", self.parameter)
+        } else {
+            write!(f, "{} ({})", self.parameter, self.name)
+        }
+    }
+}
 
 pub struct StationsManager {
-    stations: Vec<String>,
-    matcher: SkimpleMatcher,
+    stations: Vec<Station>,
+    history: History,
+    /// Refreshes an outdated cache while the stations it had are being used
+    background: Option<JoinHandle<()>>,
+    registry: StationRegistry,
 }
 
-#[allow(dead_code)]
 #[derive(Deserialize, Debug)]
 pub struct StationsInner {
     station_name: String,
     parameter: String,
+    #[serde(default)]
+    aliases: Vec<String>,
+    #[serde(default)]
+    stop_ids: Vec<String>,
 }
 
 #[derive(Deserialize, Debug)]
 pub struct Stations(pub Vec<StationsInner>);
 
+impl From<StationsInner> for Station {
+    fn from(station: StationsInner) -> Self {
+        Station {
+            parameter: station.parameter,
+            name: station.station_name,
+            aliases: station.aliases,
+            stop_ids: station.stop_ids,
+            lines: Vec::new(),
+            zone: None,
+        }
+    }
+}
+
 impl Default for StationsManager {
     fn default() -> Self {
         Self::new()
@@ -31,261 +122,565 @@ impl Default for StationsManager {
 }
 
 impl StationsManager {
+    /// Loads the stations from the cache, or from the API if there is no cache yet.
+    ///
+    /// An outdated cache is still used, and refreshed in the background for next time.
+    /// The cache is left alone entirely while recording or replaying a capture.
     pub fn new() -> Self {
-        let mut manager = StationsManager {
-            stations: Vec::new(),
-            matcher: SkimpleMatcher::default(),
+        if is_capturing() {
+            let stations = Self::fetch_stations_from_api().unwrap_or_else(|_| Self::fallback_stations());
+            return Self::with_stations(stations, None);
+        }
+
+        let (stations, background) = match Self::read_stations_from_file() {
+            Ok(cached) if stations_ttl().is_ok_and(|ttl| cached.age() <= ttl) => (cached.payload, None),
+            Ok(cached) => (
+                cached.payload,
+                Some(thread::spawn(|| {
+                    let _ = Self::refresh_cache();
+                })),
+            ),
+            Err(_) => {
+                let stations = Self::fetch_stations_from_api()
+                    .and_then(|stations| Self::save_stations_to_file(&stations).map(|_| stations));
+                (stations.unwrap_or_else(|_| Self::fallback_stations()), None)
+            }
         };
-        manager.stations = match Self::get_stations_from_file_or_api() {
-            Ok(stations) => stations,
-            Err(_) => FALLBACK_STATIONS.into_iter().map(|str| str.to_string()).collect(),
+        Self::with_stations(stations, background)
+    }
+
+    /// Fetches the stations from the API regardless of the cache, and updates the cache.
+    pub fn refreshed() -> Result<Self> {
+        Ok(Self::with_stations(Self::refresh_cache()?, None))
+    }
+
+    fn with_stations(stations: Vec<Station>, background: Option<JoinHandle<()>>) -> Self {
+        let mut manager = StationsManager {
+            stations,
+            history: History::load(),
+            background,
+            registry: StationRegistry::load(),
         };
+        manager.apply_aliases(aliases());
+        manager.apply_registry();
+        manager.apply_zones();
         manager
     }
 
-    pub fn get_stations(&self) -> &[String] {
-        &self.stations
+    fn apply_zones(&mut self) {
+        for (parameter, _, zone) in KNOWN_STATIONS {
+            if let Some(station) = self.stations.iter_mut().find(|station| station.parameter == parameter) {
+                station.zone = Some(zone.to_owned());
+            }
+        }
     }
 
-    pub fn fuzzy_search(&mut self, search: &str) -> Result<String> {
-        let mut station = self.matcher.fuzzy_best(&self.stations, search)?;
+    fn fallback_stations() -> Vec<Station> {
+        KNOWN_STATIONS
+            .into_iter()
+            .map(|(parameter, name, _)| Station::new(parameter, name))
+            .collect()
+    }
 
-        if let Some((first, _)) = station.split_once('(') {
-            station = first.trim();
+    /// Attaches aliases to the stations they refer to, by parameter or full name.
+    /// Aliases for stations that don't exist are skipped.
+    fn apply_aliases(&mut self, aliases: Vec<(String, String)>) {
+        for (alias, target) in aliases {
+            let station = self.stations.iter_mut().find(|station| {
+                station.parameter.eq_ignore_ascii_case(&target) || station.name.eq_ignore_ascii_case(&target)
+            });
+            if let Some(station) = station
+                && !station
+                    .aliases
+                    .iter()
+                    .any(|existing| existing.eq_ignore_ascii_case(&alias))
+            {
+                station.aliases.push(alias);
+            }
         }
+    }
 
-        Ok(station.into())
+    /// Attaches the Septum stop ids linked to each station, and Septum's name as an alias.
+    fn apply_registry(&mut self) {
+        for (stop_id, linked) in self.registry.links() {
+            let Some(station) = self
+                .stations
+                .iter_mut()
+                .find(|station| station.parameter == linked.parameter)
+            else {
+                continue;
+            };
+            if !station.stop_ids.contains(stop_id) {
+                station.stop_ids.push(stop_id.clone());
+            }
+            if !station.is_named(&linked.stop_name) {
+                station.aliases.push(linked.stop_name.clone());
+            }
+        }
     }
 
-    fn get_stations_from_file_or_api() -> Result<Vec<String>> {
-        match Self::read_stations_from_file() {
-            Ok(stations) => Ok(stations),
-            Err(_) => {
-                let station = Self::fetch_stations_from_api()?;
-                Self::save_stations_to_file(&station)?;
-                Ok(station)
+    pub fn get_stations(&self) -> &[Station] {
+        &self.stations
+    }
+
+    /// Picks the station that best matches `search`, and remembers it was looked up.
+    pub fn fuzzy_search(&mut self, search: &str) -> Result<Station> {
+        let station = self.fuzzy_find(search)?;
+        // Failing to record history should never fail the actual lookup
+        let _ = self.history.record(&station.parameter);
+        Ok(station)
+    }
+
+    /// Picks the station that best matches `search`, without counting it as a lookup by the user.
+    pub fn fuzzy_find(&self, search: &str) -> Result<Station> {
+        let labels: Vec<String> = self.stations.iter().map(|station| station.to_string()).collect();
+        let index = best_match(&self.search_terms(), &labels, search, |index| {
+            let lookups = self
+                .history
+                .count(&self.stations[index].parameter)
+                .min(MAX_BOOSTED_LOOKUPS);
+            lookups as i64 * BOOST_PER_LOOKUP
+        })?;
+
+        Ok(self.stations[index].clone())
+    }
+
+    /// Picks the stop on a line that best matches `search`.
+    ///
+    /// Stops are matched by Septum's name as well as every name of the station they are linked
+    /// to, so the same query works for SEPTA and Septum commands alike.
+    pub fn fuzzy_search_stop(&mut self, stops: &[LinesStationsInner], search: &str) -> Result<LinesStationsInner> {
+        self.registry.link(stops, &self.stations);
+        self.apply_registry();
+
+        let linked: Vec<Option<&Station>> = stops
+            .iter()
+            .map(|stop| {
+                self.stations
+                    .iter()
+                    .find(|station| station.stop_ids.contains(&stop.stop_id))
+            })
+            .collect();
+        let mut terms: Vec<(String, usize)> = Vec::new();
+        for (index, stop) in stops.iter().enumerate() {
+            terms.push((stop.stop_name.clone(), index));
+            if let Some(station) = linked[index] {
+                terms.extend(station.search_terms().into_iter().map(|term| (term, index)));
             }
         }
+        let labels: Vec<String> = stops.iter().map(|stop| stop.stop_name.clone()).collect();
+
+        let index = best_match(&terms, &labels, search, |index| {
+            let lookups = linked[index].map_or(0, |station| self.history.count(&station.parameter));
+            lookups.min(MAX_BOOSTED_LOOKUPS) as i64 * BOOST_PER_LOOKUP
+        })?;
+
+        if let Some(station) = linked[index] {
+            let _ = self.history.record(&station.parameter);
+        }
+        Ok(stops[index].clone())
     }
 
-    fn fetch_stations_from_api() -> Result<Vec<String>> {
-        let base_url = env::var("SeptumURL").context("SeptumURL not set, cannot fetch stations")?;
-        let result: Stations = ureq::get(format!("{}/stations", base_url))
-            .call()?
-            .body_mut()
-            .read_json()?;
-        let stations: Vec<String> = result
-            .0
+    /// Finds the stop on a line that is the given station, if the line serves it.
+    pub fn stop_on_line(&mut self, stops: &[LinesStationsInner], parameter: &str) -> Option<LinesStationsInner> {
+        self.registry.link(stops, &self.stations);
+        self.apply_registry();
+
+        let station = self.stations.iter().find(|station| station.parameter == parameter)?;
+        stops
             .iter()
-            .map(|station| {
-                if station.parameter == station.station_name {
-                    station.parameter.to_owned()
-                } else {
-                    format!("{} ({})", station.parameter, station.station_name)
+            .find(|stop| station.stop_ids.contains(&stop.stop_id))
+            .cloned()
+    }
+
+    /// Finds the line serving both stations, along with the stops to use on it.
+    ///
+    /// If several lines serve both, the user is asked to pick one on a TTY.
+    pub fn infer_line(
+        &mut self,
+        septum: &SeptumMisc,
+        orig: &str,
+        dest: &str,
+        direction: &ScheduleDirection,
+    ) -> Result<(LinesInner, LinesStationsInner, LinesStationsInner)> {
+        let from = self
+            .fuzzy_search(orig)
+            .context("Invalid station, please use `tst stations` for all valid station names")?;
+        let to = self
+            .fuzzy_search(dest)
+            .context("Invalid station, please use `tst stations` for all valid station names")?;
+        let lines = septum
+            .get_lines()
+            .context("An error occurred while getting lines, please check your Septum URL")?;
+
+        let mut serving = Vec::new();
+        for line in lines.0 {
+            // A line whose stations can't be fetched simply doesn't count
+            let Ok(stops) = septum.get_stations_for_line(&line.line_code, direction) else {
+                continue;
+            };
+            if let (Some(orig), Some(dest)) = (
+                self.stop_on_line(&stops, &from.parameter),
+                self.stop_on_line(&stops, &to.parameter),
+            ) {
+                serving.push((line, orig, dest));
+            }
+        }
+
+        let index = match serving.len() {
+            0 => {
+                return Err(anyhow!(
+                    "No line serves both {} and {}, please give the line to use",
+                    from.parameter,
+                    to.parameter
+                ));
+            }
+            1 => 0,
+            _ => {
+                let labels: Vec<String> = serving
+                    .iter()
+                    .map(|(line, _, _)| format!("{:<6}{}", line.line_code, line.line_name))
+                    .collect();
+                let question = format!(
+                    "{} and {} are on several lines, did you mean",
+                    from.parameter, to.parameter
+                );
+                let hint = "Give the line to use before the stations, e.g `tst extra schedule TRE Trenton Suburban`";
+                let choices: Vec<usize> = (0..serving.len()).collect();
+                choose(&choices, &labels, &question, hint)?.ok_or(anyhow!("Ambiguous line"))?
+            }
+        };
+        Ok(serving.swap_remove(index))
+    }
+
+    /// Fills in the lines serving each station, from Septum's station list for every line.
+    pub fn link_lines(&mut self, septum: &SeptumMisc) -> Result<()> {
+        for line in septum.get_lines()?.0 {
+            // Both directions stop at the same stations
+            let stops = septum.get_stations_for_line(&line.line_code, &ScheduleDirection::Inbound)?;
+            self.registry.link(&stops, &self.stations);
+            self.apply_registry();
+
+            for station in self.stations.iter_mut() {
+                if stops.iter().any(|stop| station.stop_ids.contains(&stop.stop_id))
+                    && !station.lines.contains(&line.line_code)
+                {
+                    station.lines.push(line.line_code.clone());
                 }
+            }
+        }
+        Ok(())
+    }
+
+    /// Ranks every station for `search` along with its score, optionally restricted to a line and fare zone.
+    pub fn search(&self, search: &str, line: Option<&str>, zone: Option<&str>) -> StationMatches {
+        let in_line = |station: &Station| {
+            line.is_none_or(|line| station.lines.iter().any(|code| code.eq_ignore_ascii_case(line)))
+        };
+        let in_zone = |station: &Station| {
+            zone.is_none_or(|zone| {
+                station
+                    .zone
+                    .as_deref()
+                    .is_some_and(|own| own.eq_ignore_ascii_case(zone))
             })
+        };
+
+        let terms: Vec<(String, usize)> = self
+            .search_terms()
+            .into_iter()
+            .filter(|(_, index)| in_line(&self.stations[*index]) && in_zone(&self.stations[*index]))
             .collect();
+
+        StationMatches(
+            rank(&terms, search)
+                .into_iter()
+                .map(|(index, score)| (self.stations[index].clone(), score))
+                .collect(),
+        )
+    }
+
+    /// Every name a station can be matched by, paired with the index of that station.
+    fn search_terms(&self) -> Vec<(String, usize)> {
+        self.stations
+            .iter()
+            .enumerate()
+            .flat_map(|(index, station)| station.search_terms().into_iter().map(move |term| (term, index)))
+            .collect()
+    }
+
+    fn stations_url() -> Result<String> {
+        let base_url = env::var("SeptumURL").context("SeptumURL not set, cannot fetch stations")?;
+        Ok(format!("{}/stations", base_url))
+    }
+
+    fn fetch_stations_from_api() -> Result<Vec<Station>> {
+        let result: Stations = get_json(&Self::stations_url()?)?;
+        Ok(result.0.into_iter().map(Station::from).collect())
+    }
+
+    /// Fetches the stations and updates the cache, never settling for a cached response.
+    fn refresh_cache() -> Result<Vec<Station>> {
+        let result: Stations = get_json_online(&Self::stations_url()?)?;
+        let stations: Vec<Station> = result.0.into_iter().map(Station::from).collect();
+        Self::save_stations_to_file(&stations)?;
         Ok(stations)
     }
 
-    fn save_stations_to_file(stations: &[String]) -> Result<()> {
-        let app_dir = ProjectDirs::from("com", "dotzenith", "TheSeptaTimes").ok_or(anyhow!("Unable to get AppDirs"))?;
-        if !app_dir.cache_dir().exists() {
-            create_dir_all(&app_dir.cache_dir())?;
+    fn save_stations_to_file(stations: &[Station]) -> Result<()> {
+        envelope::write(
+            &stations_path()?,
+            STATIONS_FORMAT_VERSION,
+            &Self::stations_url()?,
+            &stations,
+        )
+    }
+
+    fn read_stations_from_file() -> Result<Envelope<Vec<Station>>> {
+        envelope::read(
+            &stations_path()?,
+            STATIONS_FORMAT_VERSION,
+            |version, bytes| match version {
+                // The original cache only kept display labels, e.g "30th Street Station (30th Street)"
+                None => Ok(envelope::decode_legacy::<Vec<String>>(bytes)?
+                    .iter()
+                    .map(|label| Station::from_label(label))
+                    .collect()),
+                Some(version) => Err(anyhow!("Unknown station cache version {}", version)),
+            },
+        )
+    }
+}
+
+impl Drop for StationsManager {
+    fn drop(&mut self) {
+        // Give the refresh a moment to finish writing the cache, but never hold up exiting for it.
+        // Cache writes are atomic, so a refresh cut short leaves the old cache in place.
+        let Some(background) = self.background.take() else {
+            return;
+        };
+        let deadline = Instant::now() + BACKGROUND_REFRESH_GRACE;
+        while !background.is_finished() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
         }
+    }
+}
 
-        let mut file = BufWriter::new(
-            OpenOptions::new()
-                .write(true)
-                .create(true)
-                .truncate(true)
-                .open(app_dir.cache_dir().join("stations"))?,
-        );
+/// Stations ranked by how well they match a search, best first.
+pub struct StationMatches(pub Vec<(Station, i64)>);
 
-        serialize_into(&mut file, stations)?;
-        Ok(())
+impl Parse for StationMatches {
+    fn parse(&self) -> Vec<String> {
+        self.0
+            .iter()
+            .map(|(station, score)| {
+                format!(
+                    "{:<7}{:<34}{:<26}{:<16}{}",
+                    score,
+                    station.name,
+                    station.parameter,
+                    if station.lines.is_empty() {
+                        "None".to_owned()
+                    } else {
+                        station.lines.join(",")
+                    },
+                    station.zone.as_deref().unwrap_or("None"),
+                )
+            })
+            .collect()
     }
+}
 
-    fn read_stations_from_file() -> Result<Vec<String>> {
-        let app_dir = ProjectDirs::from("com", "dotzenith", "TheSeptaTimes").ok_or(anyhow!("Unable to get AppDirs"))?;
-
-        let metadata = fs::metadata(app_dir.cache_dir().join("stations"))?;
-        let time = metadata.modified().context("Unsupported platform")?;
-        let diff = SystemTime::now()
-            .duration_since(time)
-            .context("Time went backwards")?
-            .as_secs();
-        if diff > SECONDS_IN_WEEK {
-            return Err(anyhow!("Station name cache too old"));
+impl PrettyPrint for StationMatches {
+    fn print(&self) {
+        if self.0.is_empty() {
+            println!("{}", "No matching stations".yellow());
+            return;
         }
-
-        let mut f = BufReader::new(
-            OpenOptions::new()
-                .read(true)
-                .open(app_dir.cache_dir().join("stations"))?,
+        println!(
+            "{:<7}{:<34}{:<26}{:<16}{}",
+            "Score".red(),
+            "Station".yellow(),
+            "Parameter".cyan(),
+            "Lines".green(),
+            "Zone".blue(),
         );
-
-        let stations: Vec<String> = deserialize_from(&mut f)?;
-        Ok(stations)
+        for station in self.parse().iter() {
+            println!("{station}");
+        }
     }
 }
 
-const SECONDS_IN_WEEK: u64 = 604800;
-
-const FALLBACK_STATIONS: [&'static str; 155] = [
-    "9th St (9th Street)",
-    "30th Street Station (30th Street)",
-    "49th St (49th Street)",
-    "Airport Terminal A",
-    "Airport Terminal B",
-    "Airport Terminal C-D",
-    "Airport Terminal E-F",
-    "Allegheny",
-    "Allen Lane",
-    "Ambler",
-    "Angora",
-    "Ardmore",
-    "Ardsley",
-    "Bala",
-    "Berwyn",
-    "Bethayres",
-    "Bridesburg",
-    "Bristol",
-    "Bryn Mawr",
-    "Carpenter",
-    "Chalfont",
-    "Chelten Avenue",
-    "Cheltenham",
-    "Chester TC (Chester Transportation Center)",
-    "Chestnut Hill East",
-    "Chestnut Hill West",
-    "Churchmans Crossing (Churchmans Crossing, DE)",
-    "Claymont (Claymont, DE)",
-    "Clifton-Aldan",
-    "Colmar",
-    "Conshohocken",
-    "Cornwells Heights",
-    "Crestmont",
-    "Croydon",
-    "Crum Lynne",
-    "Curtis Park",
-    "Cynwyd",
-    "Daylesford",
-    "Darby",
-    "Delaware Valley College",
-    "Devon",
-    "Downingtown",
-    "Doylestown",
-    "East Falls",
-    "Eastwick Station (Eastwick)",
-    "Eddington",
-    "Eddystone",
-    "Elkins Park",
-    "Elm St (Elm Street-Norristown)",
-    "Elwyn Station (Elwyn)",
-    "Exton",
-    "Fern Rock TC (Fern Rock Transportation Center)",
-    "Fernwood (Fernwood-Yeadon)",
-    "Folcroft",
-    "Forest Hills",
-    "Ft Washington (Fort Washington)",
-    "Fortuna",
-    "Fox Chase",
-    "Germantown",
-    "Gladstone",
-    "Glenolden",
-    "Glenside",
-    "Gravers",
-    "Gwynedd Valley",
-    "Hatboro",
-    "Haverford",
-    "Highland Ave (Highland Avenue)",
-    "Highland",
-    "Holmesburg Jct (Holmesburg Junction)",
-    "Ivy Ridge",
-    "Jefferson Station",
-    "Jenkintown-Wyncote",
-    "Langhorne",
-    "Lansdale",
-    "Lansdowne",
-    "Lawndale",
-    "Levittown",
-    "Link Belt",
-    "Main St (Main Street-Norristown)",
-    "Malvern",
-    "Manayunk",
-    "Marcus Hook",
-    "Meadowbrook",
-    "Media",
-    "Melrose Park",
-    "Merion",
-    "Miquon",
-    "Morton",
-    "Moylan-Rose Valley",
-    "Mt Airy (Mt. Airy)",
-    "Narberth",
-    "Neshaminy Falls",
-    "New Britain",
-    "Newark (Newark Station)",
-    "Noble (Noble Station)",
-    "Norristown TC (Norristown Transportation Center)",
-    "North Broad St (North Broad)",
-    "North Hills",
-    "North Philadelphia",
-    "North Wales",
-    "Norwood",
-    "Olney",
-    "Oreland",
-    "Overbrook",
-    "Paoli",
-    "Penllyn",
-    "Pennbrook",
-    "Penn Medicine Station (Penn Medicine Station (University City))",
-    "Philmont",
-    "Primos",
-    "Prospect Park",
-    "Queen Lane",
-    "Radnor",
-    "Ridley Park",
-    "Rosemont",
-    "Roslyn",
-    "Rydal",
-    "Ryers",
-    "Secane",
-    "Sedgwick",
-    "Sharon Hill",
-    "Somerton",
-    "Spring Mill",
-    "St. Davids",
-    "St. Martins",
-    "Stenton",
-    "Strafford",
-    "Suburban Station",
-    "Swarthmore",
-    "Tacony",
-    "Temple U (Temple University)",
-    "Thorndale",
-    "Torresdale",
-    "Trenton (Trenton Transit Center)",
-    "Trevose",
-    "Tulpehocken",
-    "Upsal",
-    "Villanova",
-    "Wallingford",
-    "Warminster",
-    "Washington Lane",
-    "Wawa",
-    "Wayne Jct (Wayne Junction)",
-    "Wayne Station (Wayne)",
-    "West Trenton (West Trenton, NJ)",
-    "Whitford",
-    "Willow Grove",
-    "Wilmington (Wilmington, DE)",
-    "Wissahickon",
-    "Wister",
-    "Woodbourne",
-    "Wyndmoor",
-    "Wynnefield Avenue",
-    "Wynnewood",
-    "Yardley",
+/// Version of the station cache payload, bumped whenever `Station` changes shape.
+const STATIONS_FORMAT_VERSION: u32 = 1;
+
+/// How long exiting waits on a background refresh of the station cache.
+const BACKGROUND_REFRESH_GRACE: Duration = Duration::from_secs(1);
+
+/// Score added to a station for every time it was looked up before, up to `MAX_BOOSTED_LOOKUPS`.
+///
+/// Fuzzy scores for short queries are usually around 100, so a station that's used regularly
+/// ends up comfortably ahead of one that never is, without overriding a clearly better match.
+const BOOST_PER_LOOKUP: i64 = 3;
+const MAX_BOOSTED_LOOKUPS: u32 = 10;
+
+/// Every station as `(parameter, station_name, fare zone)`.
+///
+/// Used when neither the cache nor the API are available, and for fare zones since no endpoint provides them.
+const KNOWN_STATIONS: [(&str, &str, &str); 155] = [
+    ("9th St", "9th Street", "4"),
+    ("30th Street Station", "30th Street", "C"),
+    ("49th St", "49th Street", "1"),
+    ("Airport Terminal A", "Airport Terminal A", "2"),
+    ("Airport Terminal B", "Airport Terminal B", "2"),
+    ("Airport Terminal C-D", "Airport Terminal C-D", "2"),
+    ("Airport Terminal E-F", "Airport Terminal E-F", "2"),
+    ("Allegheny", "Allegheny", "1"),
+    ("Allen Lane", "Allen Lane", "1"),
+    ("Ambler", "Ambler", "3"),
+    ("Angora", "Angora", "1"),
+    ("Ardmore", "Ardmore", "2"),
+    ("Ardsley", "Ardsley", "2"),
+    ("Bala", "Bala", "1"),
+    ("Berwyn", "Berwyn", "3"),
+    ("Bethayres", "Bethayres", "3"),
+    ("Bridesburg", "Bridesburg", "1"),
+    ("Bristol", "Bristol", "4"),
+    ("Bryn Mawr", "Bryn Mawr", "2"),
+    ("Carpenter", "Carpenter", "1"),
+    ("Chalfont", "Chalfont", "4"),
+    ("Chelten Avenue", "Chelten Avenue", "1"),
+    ("Cheltenham", "Cheltenham", "1"),
+    ("Chester TC", "Chester Transportation Center", "3"),
+    ("Chestnut Hill East", "Chestnut Hill East", "1"),
+    ("Chestnut Hill West", "Chestnut Hill West", "1"),
+    ("Churchmans Crossing", "Churchmans Crossing, DE", "4"),
+    ("Claymont", "Claymont, DE", "4"),
+    ("Clifton-Aldan", "Clifton-Aldan", "2"),
+    ("Colmar", "Colmar", "4"),
+    ("Conshohocken", "Conshohocken", "2"),
+    ("Cornwells Heights", "Cornwells Heights", "3"),
+    ("Crestmont", "Crestmont", "2"),
+    ("Croydon", "Croydon", "3"),
+    ("Crum Lynne", "Crum Lynne", "3"),
+    ("Curtis Park", "Curtis Park", "2"),
+    ("Cynwyd", "Cynwyd", "1"),
+    ("Daylesford", "Daylesford", "4"),
+    ("Darby", "Darby", "1"),
+    ("Delaware Valley College", "Delaware Valley College", "4"),
+    ("Devon", "Devon", "3"),
+    ("Downingtown", "Downingtown", "4"),
+    ("Doylestown", "Doylestown", "4"),
+    ("East Falls", "East Falls", "1"),
+    ("Eastwick Station", "Eastwick", "1"),
+    ("Eddington", "Eddington", "3"),
+    ("Eddystone", "Eddystone", "3"),
+    ("Elkins Park", "Elkins Park", "2"),
+    ("Elm St", "Elm Street-Norristown", "3"),
+    ("Elwyn Station", "Elwyn", "3"),
+    ("Exton", "Exton", "4"),
+    ("Fern Rock TC", "Fern Rock Transportation Center", "1"),
+    ("Fernwood", "Fernwood-Yeadon", "1"),
+    ("Folcroft", "Folcroft", "2"),
+    ("Forest Hills", "Forest Hills", "3"),
+    ("Ft Washington", "Fort Washington", "3"),
+    ("Fortuna", "Fortuna", "4"),
+    ("Fox Chase", "Fox Chase", "1"),
+    ("Germantown", "Germantown", "1"),
+    ("Gladstone", "Gladstone", "1"),
+    ("Glenolden", "Glenolden", "2"),
+    ("Glenside", "Glenside", "2"),
+    ("Gravers", "Gravers", "1"),
+    ("Gwynedd Valley", "Gwynedd Valley", "3"),
+    ("Hatboro", "Hatboro", "3"),
+    ("Haverford", "Haverford", "2"),
+    ("Highland Ave", "Highland Avenue", "4"),
+    ("Highland", "Highland", "1"),
+    ("Holmesburg Jct", "Holmesburg Junction", "2"),
+    ("Ivy Ridge", "Ivy Ridge", "1"),
+    ("Jefferson Station", "Jefferson Station", "C"),
+    ("Jenkintown-Wyncote", "Jenkintown-Wyncote", "2"),
+    ("Langhorne", "Langhorne", "3"),
+    ("Lansdale", "Lansdale", "4"),
+    ("Lansdowne", "Lansdowne", "1"),
+    ("Lawndale", "Lawndale", "1"),
+    ("Levittown", "Levittown", "4"),
+    ("Link Belt", "Link Belt", "4"),
+    ("Main St", "Main Street-Norristown", "3"),
+    ("Malvern", "Malvern", "4"),
+    ("Manayunk", "Manayunk", "1"),
+    ("Marcus Hook", "Marcus Hook", "4"),
+    ("Meadowbrook", "Meadowbrook", "2"),
+    ("Media", "Media", "3"),
+    ("Melrose Park", "Melrose Park", "2"),
+    ("Merion", "Merion", "1"),
+    ("Miquon", "Miquon", "2"),
+    ("Morton", "Morton", "2"),
+    ("Moylan-Rose Valley", "Moylan-Rose Valley", "3"),
+    ("Mt Airy", "Mt. Airy", "1"),
+    ("Narberth", "Narberth", "1"),
+    ("Neshaminy Falls", "Neshaminy Falls", "3"),
+    ("New Britain", "New Britain", "4"),
+    ("Newark", "Newark Station", "4"),
+    ("Noble", "Noble Station", "2"),
+    ("Norristown TC", "Norristown Transportation Center", "3"),
+    ("North Broad St", "North Broad", "1"),
+    ("North Hills", "North Hills", "3"),
+    ("North Philadelphia", "North Philadelphia", "1"),
+    ("North Wales", "North Wales", "4"),
+    ("Norwood", "Norwood", "2"),
+    ("Olney", "Olney", "1"),
+    ("Oreland", "Oreland", "2"),
+    ("Overbrook", "Overbrook", "1"),
+    ("Paoli", "Paoli", "4"),
+    ("Penllyn", "Penllyn", "3"),
+    ("Pennbrook", "Pennbrook", "4"),
+    ("Penn Medicine Station", "Penn Medicine Station (University City)", "C"),
+    ("Philmont", "Philmont", "3"),
+    ("Primos", "Primos", "2"),
+    ("Prospect Park", "Prospect Park", "3"),
+    ("Queen Lane", "Queen Lane", "1"),
+    ("Radnor", "Radnor", "3"),
+    ("Ridley Park", "Ridley Park", "3"),
+    ("Rosemont", "Rosemont", "2"),
+    ("Roslyn", "Roslyn", "2"),
+    ("Rydal", "Rydal", "2"),
+    ("Ryers", "Ryers", "1"),
+    ("Secane", "Secane", "2"),
+    ("Sedgwick", "Sedgwick", "1"),
+    ("Sharon Hill", "Sharon Hill", "2"),
+    ("Somerton", "Somerton", "2"),
+    ("Spring Mill", "Spring Mill", "2"),
+    ("St. Davids", "St. Davids", "3"),
+    ("St. Martins", "St. Martins", "1"),
+    ("Stenton", "Stenton", "1"),
+    ("Strafford", "Strafford", "3"),
+    ("Suburban Station", "Suburban Station", "C"),
+    ("Swarthmore", "Swarthmore", "2"),
+    ("Tacony", "Tacony", "1"),
+    ("Temple U", "Temple University", "C"),
+    ("Thorndale", "Thorndale", "4"),
+    ("Torresdale", "Torresdale", "2"),
+    ("Trenton", "Trenton Transit Center", "NJ"),
+    ("Trevose", "Trevose", "3"),
+    ("Tulpehocken", "Tulpehocken", "1"),
+    ("Upsal", "Upsal", "1"),
+    ("Villanova", "Villanova", "2"),
+    ("Wallingford", "Wallingford", "3"),
+    ("Warminster", "Warminster", "3"),
+    ("Washington Lane", "Washington Lane", "1"),
+    ("Wawa", "Wawa", "4"),
+    ("Wayne Jct", "Wayne Junction", "1"),
+    ("Wayne Station", "Wayne", "3"),
+    ("West Trenton", "West Trenton, NJ", "NJ"),
+    ("Whitford", "Whitford", "4"),
+    ("Willow Grove", "Willow Grove", "3"),
+    ("Wilmington", "Wilmington, DE", "4"),
+    ("Wissahickon", "Wissahickon", "1"),
+    ("Wister", "Wister", "1"),
+    ("Woodbourne", "Woodbourne", "4"),
+    ("Wyndmoor", "Wyndmoor", "2"),
+    ("Wynnefield Avenue", "Wynnefield Avenue", "1"),
+    ("Wynnewood", "Wynnewood", "1"),
+    ("Yardley", "Yardley", "4"),
 ];
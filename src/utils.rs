@@ -1,3 +1,6 @@
+use anyhow::{Context, Result};
+use std::time::{SystemTime, UNIX_EPOCH};
+
 /// Formats hour and minute into 12-hour time with AM/PM.
 ///
 /// Handles SEPTA's quirky time format where hours can exceed 24
@@ -30,3 +33,69 @@ pub fn parse_datetime(datetime: Option<&str>) -> String {
     let time_part = dt.split_whitespace().nth(1).unwrap_or(dt);
     parse_time(time_part)
 }
+
+/// Formats a number of seconds as a short human readable age (e.g, "14 min").
+pub fn format_age(seconds: u64) -> String {
+    match seconds {
+        0..=59 => format!("{} sec", seconds),
+        60..=3599 => format!("{} min", seconds / 60),
+        3600..=86399 => format!("{} hr", seconds / 3600),
+        _ => format!("{} days", seconds / 86400),
+    }
+}
+
+/// Stable FNV-1a hash, used to derive file names from cache keys.
+///
+/// `DefaultHasher` is not guaranteed to be stable across Rust releases,
+/// which would silently invalidate everything on disk.
+pub fn hash_key(key: &str) -> u64 {
+    hash_bytes(key.as_bytes())
+}
+
+/// Same as `hash_key`, for arbitrary bytes (e.g, cache checksums).
+pub fn hash_bytes(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Current Unix timestamp, in seconds.
+pub fn now() -> Result<u64> {
+    Ok(SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .context("Time went backwards")?
+        .as_secs())
+}
+
+/// Strips HTML tags and common entities from a message, collapsing whitespace.
+pub fn strip_html(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => {
+                in_tag = true;
+                text.push(' ');
+            }
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Formats a number of bytes as a short human readable size (e.g, "12.4 KB").
+pub fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1048575 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1048576.0),
+    }
+}